@@ -0,0 +1,23 @@
+//! Escritura atómica de archivos: se escribe un temporal al lado y se
+//! renombra encima, así un lector (o un corte de luz) nunca ve un archivo a medias.
+
+use anyhow::Context;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).with_context(|| format!("creando dir {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("tmp");
+    {
+        let mut f =
+            fs::File::create(&tmp).with_context(|| format!("creando tmp {}", tmp.display()))?;
+        f.write_all(bytes).context("escribiendo tmp")?;
+        f.sync_all().ok();
+    }
+    fs::rename(&tmp, path)
+        .with_context(|| format!("renombrando {} -> {}", tmp.display(), path.display()))?;
+    Ok(())
+}
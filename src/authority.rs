@@ -0,0 +1,160 @@
+//! Límites por zona configurada (p.ej. un TLD lento): resoluciones de
+//! clientes por segundo y en vuelo debajo de la zona. Lo que pasa el tope se
+//! responde SERVFAIL sin consultar y se cuenta en `limited`.
+//!
+//! Se cuenta la resolución entera, no las consultas que hickory manda para
+//! hacerla: hickory 0.25 elige autoritativo y manda sin exponer dónde
+//! engancharse, así que no hay límites, SRTT ni expulsión por servidor. La
+//! zona tampoco se expulsa por fallas: cualquier cliente podría tirarla
+//! preguntando nombres rotos debajo de ella.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use hickory_proto::rr::Name;
+
+use crate::config::AuthorityConfig;
+
+/// Por qué no se resolvió una pregunta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refused {
+    /// Tope de resoluciones en vuelo de la zona.
+    Busy,
+    /// Tope de resoluciones por segundo de la zona.
+    RateLimited,
+}
+
+impl fmt::Display for Refused {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Busy => write!(f, "demasiadas resoluciones en vuelo"),
+            Self::RateLimited => write!(f, "tope de resoluciones por segundo"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZoneLimits {
+    zones: Vec<Arc<Zone>>,
+    limited: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Zone {
+    name: Name,
+    qps: u32,
+    max_inflight: usize,
+    inflight: Arc<AtomicUsize>,
+    bucket: Mutex<Bucket>,
+}
+
+/// Token bucket con ráfaga de un segundo.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl ZoneLimits {
+    pub fn from_config(cfg: &AuthorityConfig) -> anyhow::Result<Self> {
+        let mut zones = Vec::new();
+        for z in &cfg.zones {
+            let name = Name::from_ascii(z.zone.trim())
+                .map_err(|e| anyhow::anyhow!("recursor.authority.zones: zona inválida {}: {e}", z.zone))?;
+            zones.push(Arc::new(Zone {
+                name,
+                qps: z.qps,
+                max_inflight: z.max_inflight,
+                inflight: Arc::default(),
+                bucket: Mutex::new(Bucket {
+                    tokens: z.qps as f64,
+                    last: Instant::now(),
+                }),
+            }));
+        }
+        Ok(Self {
+            zones,
+            limited: AtomicU64::new(0),
+        })
+    }
+
+    /// Permiso para una resolución de `qname` (se libera al soltarlo): `None`
+    /// si no cae debajo de ninguna zona configurada.
+    pub fn zone(&self, qname: &Name) -> Result<Option<ZonePermit>, Refused> {
+        let Some(zone) = self
+            .zones
+            .iter()
+            .filter(|z| z.name.zone_of(qname))
+            .max_by_key(|z| z.name.num_labels())
+        else {
+            return Ok(None);
+        };
+        if zone.qps > 0 && !zone.bucket.lock().unwrap().take(zone.qps, Instant::now()) {
+            self.limited.fetch_add(1, Ordering::Relaxed);
+            return Err(Refused::RateLimited);
+        }
+        let inflight = InFlight::acquire(&zone.inflight, zone.max_inflight).ok_or_else(|| {
+            self.limited.fetch_add(1, Ordering::Relaxed);
+            Refused::Busy
+        })?;
+        Ok(Some(ZonePermit { _inflight: inflight }))
+    }
+
+    pub fn limited(&self) -> u64 {
+        self.limited.load(Ordering::Relaxed)
+    }
+
+    /// Líneas para `status.server.`: el contador y cada zona configurada.
+    pub fn status_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("zonas limitadas={}", self.limited())];
+        for z in &self.zones {
+            lines.push(format!(
+                "zona {} en_vuelo={} qps={}",
+                z.name,
+                z.inflight.load(Ordering::Relaxed),
+                z.qps
+            ));
+        }
+        lines
+    }
+}
+
+impl Bucket {
+    fn take(&mut self, qps: u32, now: Instant) -> bool {
+        let rate = qps as f64;
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.last = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// Una resolución en vuelo; se descuenta al soltarla. `max == 0`: sin tope.
+#[derive(Debug)]
+struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    fn acquire(counter: &Arc<AtomicUsize>, max: usize) -> Option<Self> {
+        counter
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (max == 0 || n < max).then_some(n + 1))
+            .ok()
+            .map(|_| Self(counter.clone()))
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[derive(Debug)]
+pub struct ZonePermit {
+    _inflight: InFlight,
+}
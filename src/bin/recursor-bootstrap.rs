@@ -33,8 +33,8 @@ use hickory_resolver::name_server::TokioConnectionProvider;
 use hickory_resolver::TokioResolver;
 use quick_xml::events::Event;
 use quick_xml::Reader;
+use rust_dns_recursor::atomic_file::write_atomic;
 use std::fs;
-use std::io::Write;
 use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -300,22 +300,6 @@ fn parse_socket_addr(s: &str) -> Result<SocketAddr> {
     Ok(SocketAddr::new(ip, 53))
 }
 
-fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).with_context(|| format!("creando dir {}", parent.display()))?;
-    }
-    let tmp = path.with_extension("tmp");
-    {
-        let mut f =
-            fs::File::create(&tmp).with_context(|| format!("creando tmp {}", tmp.display()))?;
-        f.write_all(bytes).context("escribiendo tmp")?;
-        f.sync_all().ok();
-    }
-    fs::rename(&tmp, path)
-        .with_context(|| format!("renombrando {} -> {}", tmp.display(), path.display()))?;
-    Ok(())
-}
-
 async fn inspect_root_anchors_xml() -> Result<()> {
     let client = reqwest::Client::builder()
         .user_agent("recursor-bootstrap/1.0")
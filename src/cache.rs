@@ -1,5 +1,10 @@
 use crate::config::CacheConfig;
+use crate::rrset_cache::RrsetCache;
+use hickory_proto::rr::{RData, Record};
 use moka::future::Cache;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -7,26 +12,28 @@ pub struct CacheKey {
     pub qname_lc: String,
     pub qtype: u16,
     pub do_bit: bool,
+    /// ECS: subred del cliente truncada al scope devuelto (None = global).
+    pub subnet: Option<(IpAddr, u8)>,
 }
 
+/// Respuesta negativa ya serializada; no se sirve vencida.
 #[derive(Debug, Clone)]
 pub struct CachedEntry {
     pub bytes: Vec<u8>,
     pub expires_at: Instant,
-    pub stale_until: Instant,
 }
 
 impl CachedEntry {
-    pub fn new(bytes: Vec<u8>, ttl: Duration, stale_window: Duration) -> Self {
-        let now = Instant::now();
-        let expires_at = now + ttl;
-        let stale_until = expires_at + stale_window;
+    pub fn new(bytes: Vec<u8>, ttl: Duration) -> Self {
         Self {
             bytes,
-            expires_at,
-            stale_until,
+            expires_at: Instant::now() + ttl,
         }
     }
+
+    pub fn is_live(&self) -> bool {
+        Instant::now() < self.expires_at
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -39,46 +46,100 @@ pub enum CacheState {
 
 #[derive(Clone)]
 pub struct DnsCaches {
-    pub answers: Cache<CacheKey, CachedEntry>,
+    /// Positivas, por RRset (ver `rrset_cache`).
+    pub rrsets: RrsetCache,
+    /// Negativas (y CNAME que terminan en NODATA), por mensaje.
     pub negative: Cache<CacheKey, CachedEntry>,
 
     /// Cache auxiliar para política 2-hit del negativo:
     /// 1er NXDOMAIN/NODATA: marca probe; 2do: se cachea en `negative`.
     pub negative_probe: Cache<CacheKey, u8>,
 
+    /// ECS: último SCOPE PREFIX visto por (clave sin subred, familia IPv6).
+    pub ecs_scope: Cache<(CacheKey, bool), u8>,
+
     pub min_ttl: Duration,
     pub max_ttl: Duration,
     pub negative_ttl: Duration,
 
     pub prefetch_threshold: Duration,
-    pub stale_window: Duration,
 
     pub negative_cfg: crate::config::NegativeCacheConfig,
+
+    pub stats: Arc<CacheStats>,
+}
+
+/// Contadores del cache del servidor. El de hickory es sólo de trabajo de la
+/// recursión (ver `recursor_engine`); los vaciados lo alcanzan también.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub hits: AtomicU64,
+    /// Respuestas servidas vencidas o por vencer (con revalidación).
+    pub stale_hits: AtomicU64,
+    pub negative_hits: AtomicU64,
+    pub misses: AtomicU64,
+    pub inserts: AtomicU64,
+    pub flushes: AtomicU64,
+}
+
+impl CacheStats {
+    pub fn count(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 impl DnsCaches {
-    pub fn new(cfg: &CacheConfig) -> Self {
+    /// `max_cname_chain`: largo máximo de las cadenas CNAME que se arman
+    /// desde el cache (`recursor.max_cname_chain`).
+    pub fn new(cfg: &CacheConfig, max_cname_chain: usize) -> Self {
         let prefetch_threshold = Duration::from_secs(cfg.prefetch_threshold_secs);
-        let stale_window = Duration::from_secs(cfg.stale_window_secs);
 
         Self {
-            answers: Cache::builder().max_capacity(cfg.answer_cache_size).build(),
+            rrsets: RrsetCache::new(cfg, max_cname_chain),
             negative: Cache::builder().max_capacity(cfg.negative_cache_size).build(),
             negative_probe: Cache::builder()
                 .max_capacity(cfg.negative_cache_size)
                 .time_to_live(Duration::from_secs(cfg.negative.probe_ttl_secs))
                 .build(),
+            ecs_scope: Cache::builder()
+                .max_capacity(cfg.answer_cache_size)
+                .time_to_live(Duration::from_secs(cfg.max_ttl))
+                .build(),
             min_ttl: Duration::from_secs(cfg.min_ttl),
             max_ttl: Duration::from_secs(cfg.max_ttl),
             negative_ttl: Duration::from_secs(cfg.negative_ttl),
             prefetch_threshold,
-            stale_window,
             negative_cfg: cfg.negative.clone(),
+            stats: Arc::default(),
         }
     }
 
-    pub fn clamp_ttl(&self, ttl: Duration) -> Duration {
-        ttl.clamp(self.min_ttl, self.max_ttl)
+    /// Vacía todos los caches del front (el del recursor lo vacía `RecursorEngine`).
+    pub fn flush(&self) {
+        self.rrsets.invalidate_all();
+        self.negative.invalidate_all();
+        self.negative_probe.invalidate_all();
+        self.ecs_scope.invalidate_all();
+        CacheStats::count(&self.stats.flushes);
+    }
+
+    /// Línea para `status.server.`.
+    pub fn status_line(&self) -> String {
+        let s = &self.stats;
+        let load = |c: &AtomicU64| c.load(Ordering::Relaxed);
+        format!(
+            "cache rrsets={} negative={} hits={} stale_hits={} negative_hits={} misses={} inserts={} flushes={} min_ttl={}s max_ttl={}s",
+            self.rrsets.entry_count(),
+            self.negative.entry_count(),
+            load(&s.hits),
+            load(&s.stale_hits),
+            load(&s.negative_hits),
+            load(&s.misses),
+            load(&s.inserts),
+            load(&s.flushes),
+            self.min_ttl.as_secs(),
+            self.max_ttl.as_secs(),
+        )
     }
 
     pub fn clamp_negative_ttl(&self, ttl: Duration) -> Duration {
@@ -87,24 +148,42 @@ impl DnsCaches {
         ttl.clamp(min_ttl, max_ttl)
     }
 
-    pub fn stale_window(&self) -> Duration {
-        self.stale_window
+    /// TTL negativo según RFC 2308 §5: min(TTL de la SOA, MINIMUM), con el
+    /// clamp del cache negativo. Sin SOA se usa `negative_ttl`.
+    pub fn negative_ttl_for(&self, authority: &[Record]) -> Duration {
+        let from_soa = authority.iter().find_map(|r| match r.data() {
+            RData::SOA(soa) => Some(r.ttl().min(soa.minimum())),
+            _ => None,
+        });
+        let ttl = from_soa.map_or(self.negative_ttl, |secs| Duration::from_secs(secs.into()));
+        self.clamp_negative_ttl(ttl)
     }
 
-    pub fn classify(&self, entry: &CachedEntry) -> CacheState {
+    pub fn classify(&self, expires_at: Instant, stale_until: Instant) -> CacheState {
         let now = Instant::now();
 
-        if now < entry.expires_at {
-            let remaining = entry.expires_at - now;
+        if now < expires_at {
+            let remaining = expires_at - now;
             if remaining <= self.prefetch_threshold {
                 CacheState::NearExpiry
             } else {
                 CacheState::Fresh
             }
-        } else if now < entry.stale_until {
+        } else if now < stale_until {
             CacheState::Stale
         } else {
             CacheState::Dead
         }
     }
 }
+
+impl CacheKey {
+    /// Clave para una respuesta con ECS: `source` truncada a `scope` (acotado
+    /// al prefijo enviado). Scope 0 = la respuesta vale para todos.
+    pub fn with_subnet(&self, source: IpAddr, source_prefix: u8, scope: u8) -> Self {
+        let prefix = scope.min(source_prefix);
+        let mut key = self.clone();
+        key.subnet = (prefix > 0).then(|| (crate::ecs::truncate(source, prefix), prefix));
+        key
+    }
+}
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
@@ -15,6 +16,15 @@ pub struct AppConfig {
     pub filters: FiltersConfig,
     pub cache: CacheConfig,
     pub recursor: RecursorConfig,
+
+    #[serde(default)]
+    pub padding: PaddingConfig,
+
+    #[serde(default)]
+    pub forwarder: ForwarderConfig,
+
+    #[serde(default)]
+    pub resolution: ResolutionConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -94,7 +104,6 @@ pub struct NegativeCacheConfig {
 
     /// Cachear NODATA (NOERROR pero sin answers para ese qtype).
     #[serde(default = "d_true")]
-    #[allow(dead_code)]
     pub cache_nodata: bool,
 
     /// Política 2-hit: 1er hit = probe corto, 2do hit = se cachea.
@@ -115,13 +124,583 @@ pub struct NegativeCacheConfig {
 #[derive(Debug, Clone, Deserialize)]
 pub struct RecursorConfig {
     pub ns_cache_size: usize,
-    pub record_cache_size: usize,
     pub recursion_limit: u8,
     pub ns_recursion_limit: u8,
+    /// Timeout de cada intento; los reintentos y el deadline total van en `[resolution]`.
     pub timeout_ms: u64,
-    pub attempts: usize,
+    /// Obsoleto: los reintentos los define `[resolution]`. Se acepta (con un
+    /// warning) para no romper configs existentes.
+    #[serde(default)]
+    pub attempts: Option<usize>,
     pub case_randomization: bool,
     pub dnssec: String,
+
+    /// Trust anchors DNSSEC (DNSKEY o DS, formato de zona). Sin esto se usan
+    /// los KSK de la raíz que trae hickory.
+    #[serde(default)]
+    pub trust_anchor_file: Option<String>,
+
+    /// Rollover automático del anchor de la raíz (RFC 5011).
+    #[serde(default)]
+    pub rfc5011: Rfc5011Config,
+
+    /// Negative trust anchors (RFC 7646) cargados al arrancar.
+    #[serde(default)]
+    pub nta: Vec<NtaConfig>,
+
+    /// Duración de los NTA agregados en caliente (`<dominio>.nta-add.server.`).
+    #[serde(default = "d_nta_lifetime")]
+    pub nta_lifetime_secs: u64,
+
+    /// Sintetizar NXDOMAIN/NODATA desde NSEC/NSEC3 validados (RFC 8198).
+    /// Sólo tiene efecto con `dnssec = "validate"`.
+    #[serde(default)]
+    pub aggressive_nsec: bool,
+    /// Máximo de rangos NSEC/NSEC3 guardados.
+    #[cfg(feature = "dnssec")]
+    #[serde(default = "d_aggressive_nsec_size")]
+    pub aggressive_nsec_size: usize,
+    /// Sin `--features dnssec` sólo se lee para rechazarlo.
+    #[cfg(not(feature = "dnssec"))]
+    #[serde(default)]
+    pub aggressive_nsec_size: Option<usize>,
+
+    /// Copia local de la zona raíz (RFC 8806); sin esto se consultan los roots.
+    #[cfg(feature = "dnssec")]
+    #[serde(default)]
+    pub root_mirror: Option<RootMirrorConfig>,
+    /// Sin `--features dnssec` sólo se lee para rechazarla.
+    #[cfg(not(feature = "dnssec"))]
+    #[serde(default)]
+    pub root_mirror: Option<toml::Value>,
+
+    /// Priming de la raíz (RFC 8109): el set de roots sale de `. NS` y no de
+    /// los hints.
+    #[serde(default = "d_true")]
+    pub priming: bool,
+    /// Tope entre primings; se repite antes si vence el TTL de `. NS`.
+    #[serde(default = "d_priming_interval")]
+    pub priming_interval_secs: u64,
+
+    /// Familias con las que se habla a roots y autoritativos.
+    #[serde(default = "d_true")]
+    pub ipv4: bool,
+    #[serde(default = "d_true")]
+    pub ipv6: bool,
+
+    /// Largo máximo de una cadena CNAME en la respuesta.
+    #[serde(default = "d_max_cname_chain")]
+    pub max_cname_chain: usize,
+
+    /// Límites por autoritativo (en vuelo, expulsión, tasa por zona).
+    #[serde(default)]
+    pub authority: AuthorityConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthorityConfig {
+    /// Zonas con tope propio (p.ej. un TLD lento).
+    #[serde(default)]
+    pub zones: Vec<ZoneLimitConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneLimitConfig {
+    pub zone: String,
+    /// Resoluciones por segundo debajo de la zona (0 = sin tope).
+    #[serde(default)]
+    pub qps: u32,
+    /// Resoluciones en vuelo debajo de la zona (0 = sin tope).
+    #[serde(default)]
+    pub max_inflight: usize,
+}
+
+
+/// Zona raíz servida en loopback para el recursor (RFC 8806).
+#[cfg(feature = "dnssec")]
+#[derive(Debug, Clone, Deserialize)]
+pub struct RootMirrorConfig {
+    /// Copia de la zona (formato de https://www.internic.net/domain/root.zone,
+    /// un registro por línea).
+    pub file: String,
+    /// Dirección (loopback) donde se sirve la zona al recursor.
+    #[serde(default = "d_root_mirror_listen")]
+    pub listen: String,
+    /// Servidores `ip:port` (o IP, puerto 53) de los que se baja la zona por AXFR.
+    #[serde(default)]
+    pub axfr_sources: Vec<String>,
+    /// Cada cuánto se revisa la SOA de las fuentes; default el refresh de la SOA.
+    #[serde(default)]
+    pub refresh_secs: Option<u64>,
+    /// Rechazar copias sin un ZONEMD (RFC 8976) que coincida.
+    #[serde(default = "d_true")]
+    pub require_zonemd: bool,
+}
+
+#[cfg(feature = "dnssec")]
+fn d_root_mirror_listen() -> String {
+    "127.0.0.1:5301".to_string()
+}
+
+fn d_priming_interval() -> u64 {
+    86400
+}
+
+fn d_max_cname_chain() -> usize {
+    8
+}
+
+
+#[cfg(feature = "dnssec")]
+fn d_aggressive_nsec_size() -> usize {
+    10000
+}
+
+fn d_nta_lifetime() -> u64 {
+    86400
+}
+
+/// Dominio (y subdominios) en el que no se valida DNSSEC hasta que venza.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NtaConfig {
+    pub domain: String,
+    #[serde(default = "d_nta_lifetime")]
+    pub lifetime_secs: u64,
+}
+
+fn d_hold_down_days() -> u64 {
+    30
+}
+fn d_rfc5011_refresh() -> u64 {
+    43200
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rfc5011Config {
+    /// Archivo donde se persiste el estado de las claves de la raíz. Sin
+    /// esto no hay rollover automático. Si no existe se crea con los anchors
+    /// de la raíz de `trust_anchor_file` (o los de hickory).
+    #[serde(default)]
+    pub state_file: Option<String>,
+
+    /// Cada cuánto se consulta `. DNSKEY` (mínimo 1 hora).
+    #[serde(default = "d_rfc5011_refresh")]
+    pub refresh_secs: u64,
+
+    /// Días que una clave nueva tiene que verse sin cortes antes de confiar en ella.
+    #[serde(default = "d_hold_down_days")]
+    pub add_hold_down_days: u64,
+
+    /// Días que se recuerda una clave revocada antes de borrarla del estado.
+    #[serde(default = "d_hold_down_days")]
+    pub remove_hold_down_days: u64,
+}
+
+impl Rfc5011Config {
+    /// ¿Se tocó algo de la sección? Sin `state_file` el resto no tiene efecto.
+    pub fn is_set(&self) -> bool {
+        self.state_file.is_some()
+            || self.refresh_secs != d_rfc5011_refresh()
+            || self.add_hold_down_days != d_hold_down_days()
+            || self.remove_hold_down_days != d_hold_down_days()
+    }
+}
+
+impl Default for Rfc5011Config {
+    fn default() -> Self {
+        Self {
+            state_file: None,
+            refresh_secs: d_rfc5011_refresh(),
+            add_hold_down_days: d_hold_down_days(),
+            remove_hold_down_days: d_hold_down_days(),
+        }
+    }
+}
+
+fn d_deadline() -> u64 {
+    5000
+}
+fn d_resolution_attempts() -> u32 {
+    3
+}
+fn d_retry_base() -> u64 {
+    50
+}
+fn d_retry_max() -> u64 {
+    1000
+}
+
+/// Reintentos y deadline de una resolución (forwarder y recursor).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResolutionConfig {
+    /// Presupuesto total por consulta: intentos + esperas.
+    #[serde(default = "d_deadline")]
+    pub deadline_ms: u64,
+
+    /// Intentos totales; sólo se reintentan timeouts y fallas (no NXDOMAIN/NODATA).
+    #[serde(default = "d_resolution_attempts")]
+    pub attempts: u32,
+
+    /// Backoff exponencial entre intentos: base * 2^n, con tope y jitter.
+    #[serde(default = "d_retry_base")]
+    pub backoff_base_ms: u64,
+    #[serde(default = "d_retry_max")]
+    pub backoff_max_ms: u64,
+    #[serde(default = "d_jitter")]
+    pub jitter: f64,
+}
+
+impl Default for ResolutionConfig {
+    fn default() -> Self {
+        Self {
+            deadline_ms: d_deadline(),
+            attempts: d_resolution_attempts(),
+            backoff_base_ms: d_retry_base(),
+            backoff_max_ms: d_retry_max(),
+            jitter: d_jitter(),
+        }
+    }
+}
+
+fn d_strategy() -> String {
+    "lowest_srtt".to_string()
+}
+fn d_stats_interval() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForwarderConfig {
+    /// Selección de upstream: ordered | round_robin | random | lowest_srtt.
+    #[serde(default = "d_strategy")]
+    pub strategy: String,
+
+    /// Peso por upstream (clave = entrada de `upstreams`); sólo para `random`. Default 1.
+    #[serde(default)]
+    pub weights: HashMap<String, u32>,
+
+    /// Cada cuántos segundos se loguean las estadísticas por upstream (0 = nunca).
+    #[serde(default = "d_stats_interval")]
+    pub stats_interval_secs: u64,
+
+    /// Health checks activos + circuit breaker.
+    #[serde(default)]
+    pub health: HealthConfig,
+
+    // --- ResolverOpts de hickory (uno por upstream) ---
+    /// Timeout por consulta a un upstream.
+    #[serde(default = "d_fwd_timeout")]
+    pub timeout_ms: u64,
+
+    /// Obsoleto: hickory hace un solo intento por upstream y los reintentos
+    /// los define `[resolution]`. Se acepta (con un warning).
+    #[serde(default)]
+    pub attempts: Option<usize>,
+
+    #[serde(default)]
+    pub edns0: bool,
+
+    /// ipv4_only | ipv6_only | ipv4_and_ipv6 | ipv6_then_ipv4 | ipv4_then_ipv6
+    #[serde(default = "d_ip_strategy")]
+    pub ip_strategy: String,
+
+    /// Cache interno de hickory (registros). 0 = deshabilitado: el único
+    /// cache es `DnsCaches`.
+    #[serde(default)]
+    pub cache_size: usize,
+
+    /// Consultas en paralelo dentro del pool de un upstream (0/1 = en serie).
+    #[serde(default = "d_fwd_concurrent")]
+    pub num_concurrent_reqs: usize,
+
+    /// Hedging: si el upstream no contestó en este tiempo se consulta también
+    /// al siguiente y gana la primera respuesta válida (0 = deshabilitado).
+    #[serde(default)]
+    pub hedge_delay_ms: u64,
+
+    /// Usar el p95 de latencia del upstream como delay (acotado por `hedge_delay_ms`).
+    #[serde(default)]
+    pub hedge_p95: bool,
+
+    /// Forwarder transparente: se reenvía el mensaje del cliente (EDNS, CD/DO)
+    /// y se devuelve la respuesta del upstream con todas sus secciones.
+    #[serde(default)]
+    pub passthrough: bool,
+
+    /// 0x20: capitalización aleatoria de la pregunta enviada al upstream.
+    #[serde(default)]
+    pub case_randomization: bool,
+
+    /// Validación de respuestas de upstream (anti-spoofing).
+    #[serde(default)]
+    pub validation: ValidationConfig,
+
+    /// Comparación de respuestas entre upstreams para zonas sensibles.
+    #[serde(default)]
+    pub consistency: ConsistencyConfig,
+
+    /// EDNS Client Subnet (RFC 7871); requiere `passthrough`.
+    #[serde(default)]
+    pub ecs: EcsConfig,
+}
+
+impl Default for ForwarderConfig {
+    fn default() -> Self {
+        Self {
+            strategy: d_strategy(),
+            weights: HashMap::new(),
+            stats_interval_secs: d_stats_interval(),
+            health: HealthConfig::default(),
+            timeout_ms: d_fwd_timeout(),
+            attempts: None,
+            edns0: false,
+            ip_strategy: d_ip_strategy(),
+            cache_size: 0,
+            num_concurrent_reqs: d_fwd_concurrent(),
+            hedge_delay_ms: 0,
+            hedge_p95: false,
+            passthrough: false,
+            case_randomization: false,
+            validation: ValidationConfig::default(),
+            consistency: ConsistencyConfig::default(),
+            ecs: EcsConfig::default(),
+        }
+    }
+}
+
+fn d_fwd_timeout() -> u64 {
+    5000
+}
+fn d_ip_strategy() -> String {
+    "ipv4_then_ipv6".to_string()
+}
+fn d_fwd_concurrent() -> usize {
+    2
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidationConfig {
+    /// Rechazar respuestas con pregunta distinta, registros fuera de
+    /// bailiwick o TTLs absurdos (se pasa al siguiente upstream).
+    #[serde(default = "d_true")]
+    pub enabled: bool,
+
+    /// TTL máximo aceptado; por encima la respuesta se descarta entera.
+    /// Siempre se rechaza > 2^31-1 (RFC 2181 §8).
+    #[serde(default = "d_absurd_ttl")]
+    pub max_ttl_secs: u32,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_ttl_secs: d_absurd_ttl(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConsistencyConfig {
+    /// Sufijos (zonas) en los que se consulta a varios upstreams y se comparan
+    /// los RRsets de answer. Vacío = deshabilitado.
+    #[serde(default)]
+    pub suffixes: Vec<String>,
+
+    /// Qué hacer ante diferencias: prefer_primary | servfail | majority (3 upstreams).
+    #[serde(default = "d_consistency_policy")]
+    pub policy: String,
+}
+
+impl Default for ConsistencyConfig {
+    fn default() -> Self {
+        Self {
+            suffixes: Vec::new(),
+            policy: d_consistency_policy(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EcsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Prefijos con los que se trunca la IP del cliente.
+    #[serde(default = "d_ecs_v4")]
+    pub ipv4_prefix: u8,
+    #[serde(default = "d_ecs_v6")]
+    pub ipv6_prefix: u8,
+
+    /// Upstreams (entradas de `upstreams`) a los que se manda ECS. Vacío = todos.
+    #[serde(default)]
+    pub upstreams: Vec<String>,
+
+    /// Sufijos para los que se manda ECS. Vacío = todos.
+    #[serde(default)]
+    pub domains: Vec<String>,
+
+    /// ECS que trae el cliente: strip (se ignora y se usa su IP) | honour
+    /// (se respeta, acotado a los prefijos de arriba).
+    #[serde(default = "d_ecs_client")]
+    pub client_ecs: String,
+}
+
+impl Default for EcsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ipv4_prefix: d_ecs_v4(),
+            ipv6_prefix: d_ecs_v6(),
+            upstreams: Vec::new(),
+            domains: Vec::new(),
+            client_ecs: d_ecs_client(),
+        }
+    }
+}
+
+fn d_ecs_v4() -> u8 {
+    24
+}
+fn d_ecs_v6() -> u8 {
+    56
+}
+fn d_ecs_client() -> String {
+    "strip".to_string()
+}
+
+fn d_consistency_policy() -> String {
+    "prefer_primary".to_string()
+}
+
+fn d_absurd_ttl() -> u32 {
+    // 1 año
+    31_536_000
+}
+
+fn d_probe_name() -> String {
+    ".".to_string()
+}
+fn d_probe_qtype() -> String {
+    "NS".to_string()
+}
+fn d_probe_interval() -> u64 {
+    10
+}
+fn d_probe_timeout() -> u64 {
+    1500
+}
+fn d_failure_threshold() -> u32 {
+    3
+}
+fn d_backoff_base() -> u64 {
+    5
+}
+fn d_backoff_max() -> u64 {
+    300
+}
+fn d_jitter() -> f64 {
+    0.2
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HealthConfig {
+    /// Probes periódicos a cada upstream.
+    #[serde(default = "d_true")]
+    pub enabled: bool,
+
+    /// Consulta usada como probe (no pasa por cache).
+    #[serde(default = "d_probe_name")]
+    pub probe_name: String,
+    #[serde(default = "d_probe_qtype")]
+    pub probe_qtype: String,
+
+    #[serde(default = "d_probe_interval")]
+    pub interval_secs: u64,
+    #[serde(default = "d_probe_timeout")]
+    pub timeout_ms: u64,
+
+    /// Fallas consecutivas (tráfico real o probes) para expulsar un upstream.
+    #[serde(default = "d_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// Backoff exponencial de la expulsión: base * 2^n, con tope y jitter.
+    #[serde(default = "d_backoff_base")]
+    pub backoff_base_secs: u64,
+    #[serde(default = "d_backoff_max")]
+    pub backoff_max_secs: u64,
+    #[serde(default = "d_jitter")]
+    pub jitter: f64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            probe_name: d_probe_name(),
+            probe_qtype: d_probe_qtype(),
+            interval_secs: d_probe_interval(),
+            timeout_ms: d_probe_timeout(),
+            failure_threshold: d_failure_threshold(),
+            backoff_base_secs: d_backoff_base(),
+            backoff_max_secs: d_backoff_max(),
+            jitter: d_jitter(),
+        }
+    }
+}
+
+/// Padding EDNS(0) (RFC 7830) por transporte. UDP plano nunca se rellena.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaddingConfig {
+    /// Respuestas a clientes, según el transporte del listener.
+    #[serde(default = "d_padding_responses")]
+    pub responses: TransportPadding,
+
+    /// Consultas hacia upstreams, según el transporte del upstream.
+    #[serde(default = "d_padding_queries")]
+    pub queries: TransportPadding,
+}
+
+impl Default for PaddingConfig {
+    fn default() -> Self {
+        Self {
+            responses: d_padding_responses(),
+            queries: d_padding_queries(),
+        }
+    }
+}
+
+/// Tamaño de bloque en bytes por transporte (0 = sin padding).
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct TransportPadding {
+    #[serde(default)]
+    pub tcp: u16,
+    #[serde(default)]
+    pub tls: u16,
+    #[serde(default)]
+    pub https: u16,
+    #[serde(default)]
+    pub quic: u16,
+}
+
+/// RFC 8467: respuestas en bloques de 468 bytes.
+fn d_padding_responses() -> TransportPadding {
+    TransportPadding {
+        tcp: 0,
+        tls: 468,
+        https: 468,
+        quic: 468,
+    }
+}
+
+/// RFC 8467: consultas en bloques de 128 bytes.
+fn d_padding_queries() -> TransportPadding {
+    TransportPadding {
+        tcp: 0,
+        tls: 128,
+        https: 128,
+        quic: 128,
+    }
 }
 
 impl AppConfig {
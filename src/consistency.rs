@@ -0,0 +1,151 @@
+//! Chequeo de consistencia entre upstreams (detección de envenenamiento).
+//!
+//! Para los sufijos configurados el forwarder consulta a 2 upstreams (3 con
+//! `majority`) y compara los RRsets de answer. Las diferencias se loguean con
+//! ambas respuestas, se cuentan y se resuelven según la política.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::{Name, Record, RecordType};
+
+use crate::config::ConsistencyConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Se usa la respuesta del primer upstream (sólo se registra la diferencia).
+    PreferPrimary,
+    /// Ante cualquier diferencia se responde SERVFAIL.
+    ServFail,
+    /// Se consulta a 3 upstreams y gana la respuesta que tengan al menos 2.
+    Majority,
+}
+
+impl Policy {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let x = s.trim().to_ascii_lowercase();
+        match x.as_str() {
+            "prefer_primary" | "primary" => Ok(Self::PreferPrimary),
+            "servfail" => Ok(Self::ServFail),
+            "majority" => Ok(Self::Majority),
+            _ => anyhow::bail!("forwarder.consistency.policy debe ser: prefer_primary | servfail | majority"),
+        }
+    }
+
+    /// Cuántos upstreams hay que consultar.
+    pub fn fanout(&self) -> usize {
+        match self {
+            Self::Majority => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// Qué respuesta usar tras comparar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Use(usize),
+    ServFail,
+}
+
+pub struct Consistency {
+    suffixes: Vec<Name>,
+    policy: Policy,
+    checks: AtomicU64,
+    mismatches: AtomicU64,
+    servfails: AtomicU64,
+}
+
+impl Consistency {
+    pub fn from_config(cfg: &ConsistencyConfig) -> anyhow::Result<Option<Self>> {
+        if cfg.suffixes.is_empty() {
+            return Ok(None);
+        }
+        let suffixes = cfg
+            .suffixes
+            .iter()
+            .map(|s| {
+                Name::from_ascii(s.trim_end_matches('.'))
+                    .map(|n| n.to_lowercase())
+                    .map_err(|e| anyhow::anyhow!("forwarder.consistency.suffixes inválido: {s}: {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Some(Self {
+            suffixes,
+            policy: Policy::parse(&cfg.policy)?,
+            checks: AtomicU64::new(0),
+            mismatches: AtomicU64::new(0),
+            servfails: AtomicU64::new(0),
+        }))
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    pub fn applies(&self, name: &Name) -> bool {
+        self.suffixes.iter().any(|s| s.zone_of(name))
+    }
+
+    /// Compara las huellas de las respuestas válidas (en orden de upstream:
+    /// la primera es la del primario). Con menos de 2 no hay nada que comparar.
+    pub fn decide(&self, what: &str, answers: &[(String, Vec<String>)]) -> Verdict {
+        if answers.len() < 2 {
+            return Verdict::Use(0);
+        }
+        self.checks.fetch_add(1, Ordering::Relaxed);
+
+        let primary = &answers[0].1;
+        if answers.iter().all(|(_, fp)| fp == primary) {
+            return Verdict::Use(0);
+        }
+
+        self.mismatches.fetch_add(1, Ordering::Relaxed);
+        for (upstream, fp) in answers {
+            tracing::warn!("inconsistencia en {what}: {upstream} => [{}]", fp.join(" | "));
+        }
+
+        let verdict = match self.policy {
+            Policy::PreferPrimary => Verdict::Use(0),
+            Policy::ServFail => Verdict::ServFail,
+            Policy::Majority => answers
+                .iter()
+                .position(|(_, fp)| answers.iter().filter(|(_, other)| other == fp).count() >= 2)
+                .map_or(Verdict::ServFail, Verdict::Use),
+        };
+        if verdict == Verdict::ServFail {
+            self.servfails.fetch_add(1, Ordering::Relaxed);
+        }
+        verdict
+    }
+}
+
+impl fmt::Display for Consistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "consistencia policy={:?} sufijos={} checks={} mismatches={} servfail={}",
+            self.policy,
+            self.suffixes.len(),
+            self.checks.load(Ordering::Relaxed),
+            self.mismatches.load(Ordering::Relaxed),
+            self.servfails.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Huella comparable de una respuesta: rcode + answers normalizados (sin
+/// TTL, owner en minúsculas, ordenados). Se ignoran las RRSIG: dos upstreams
+/// pueden tener firmas distintas igualmente válidas.
+pub fn fingerprint<'a>(rcode: ResponseCode, records: impl Iterator<Item = &'a Record>) -> Vec<String> {
+    let mut fp: Vec<String> = records
+        .filter(|r| r.record_type() != RecordType::RRSIG)
+        .map(|r| format!("{} {} {}", r.name().to_lowercase(), r.record_type(), r.data()))
+        .collect();
+    fp.sort();
+    fp.dedup();
+    fp.insert(0, rcode.to_string());
+    fp
+}
@@ -0,0 +1,141 @@
+//! EDNS Client Subnet (RFC 7871) para el forwarder pass-through.
+//!
+//! Se agrega a la consulta saliente la subred del cliente truncada (/24 y /56
+//! por defecto), sólo hacia los upstreams y dominios permitidos. El cache se
+//! indexa por la subred truncada al SCOPE que devuelve el upstream, para no
+//! servir a una subred la respuesta pensada para otra.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use anyhow::Context;
+use hickory_proto::op::Edns;
+use hickory_proto::rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption};
+use hickory_proto::rr::Name;
+
+use crate::config::EcsConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientEcs {
+    /// Se descarta el ECS del cliente y se usa su IP de origen.
+    Strip,
+    /// Se respeta el ECS del cliente (prefijo acotado a los configurados).
+    Honour,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ecs {
+    ipv4_prefix: u8,
+    ipv6_prefix: u8,
+    upstreams: Vec<SocketAddr>,
+    domains: Vec<Name>,
+    client_ecs: ClientEcs,
+}
+
+impl Ecs {
+    pub fn from_config(cfg: &EcsConfig) -> anyhow::Result<Option<Self>> {
+        if !cfg.enabled {
+            return Ok(None);
+        }
+
+        let upstreams = cfg
+            .upstreams
+            .iter()
+            .map(|u| {
+                u.parse()
+                    .with_context(|| format!("forwarder.ecs.upstreams inválido: {u}"))
+            })
+            .collect::<anyhow::Result<Vec<SocketAddr>>>()?;
+
+        let domains = cfg
+            .domains
+            .iter()
+            .map(|d| {
+                Name::from_ascii(d.trim_end_matches('.'))
+                    .map(|n| n.to_lowercase())
+                    .with_context(|| format!("forwarder.ecs.domains inválido: {d}"))
+            })
+            .collect::<anyhow::Result<Vec<Name>>>()?;
+
+        let client_ecs = match cfg.client_ecs.trim().to_ascii_lowercase().as_str() {
+            "strip" => ClientEcs::Strip,
+            "honour" | "honor" => ClientEcs::Honour,
+            _ => anyhow::bail!("forwarder.ecs.client_ecs debe ser: strip | honour"),
+        };
+
+        Ok(Some(Self {
+            ipv4_prefix: cfg.ipv4_prefix.min(32),
+            ipv6_prefix: cfg.ipv6_prefix.min(128),
+            upstreams,
+            domains,
+            client_ecs,
+        }))
+    }
+
+    pub fn client_ecs(&self) -> ClientEcs {
+        self.client_ecs
+    }
+
+    pub fn applies_to_domain(&self, name: &Name) -> bool {
+        self.domains.is_empty() || self.domains.iter().any(|d| d.zone_of(name))
+    }
+
+    pub fn applies_to_upstream(&self, addr: &SocketAddr) -> bool {
+        self.upstreams.is_empty() || self.upstreams.contains(addr)
+    }
+
+    /// Subred a enviar para una consulta de `client` (con el ECS que haya
+    /// mandado, si lo hay). `None` = no mandar ECS.
+    pub fn source_for(&self, client: IpAddr, client_opt: Option<&ClientSubnet>) -> Option<ClientSubnet> {
+        let (addr, prefix) = match (self.client_ecs, client_opt) {
+            (ClientEcs::Honour, Some(c)) => {
+                // RFC 7871 §7.1.2: SOURCE PREFIX 0 = el cliente pide no mandar ECS.
+                if c.source_prefix() == 0 {
+                    return None;
+                }
+                (c.addr(), c.source_prefix())
+            }
+            _ => (client.to_canonical(), u8::MAX),
+        };
+
+        let max = match addr {
+            IpAddr::V4(_) => self.ipv4_prefix,
+            IpAddr::V6(_) => self.ipv6_prefix,
+        };
+        let prefix = prefix.min(max);
+        if prefix == 0 {
+            return None;
+        }
+        Some(ClientSubnet::new(truncate(addr, prefix), prefix, 0))
+    }
+}
+
+/// Pone en cero los bits de `addr` más allá de `prefix`.
+pub fn truncate(addr: IpAddr, prefix: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(a) => {
+            let bits = u32::from(a);
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix.min(32))).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(bits & mask))
+        }
+        IpAddr::V6(a) => {
+            let bits = u128::from(a);
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix.min(128))).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(bits & mask))
+        }
+    }
+}
+
+/// Opción ECS de un EDNS, si la tiene.
+pub fn subnet_of(edns: &Edns) -> Option<&ClientSubnet> {
+    match edns.option(EdnsCode::Subnet) {
+        Some(EdnsOption::Subnet(s)) => Some(s),
+        _ => None,
+    }
+}
+
+/// RFC 7871 §7.3: la respuesta tiene que repetir FAMILY, SOURCE PREFIX y
+/// ADDRESS de la consulta; si no, se descarta.
+pub fn response_matches(sent: &ClientSubnet, got: &ClientSubnet) -> bool {
+    got.source_prefix() == sent.source_prefix()
+        && truncate(got.addr(), sent.source_prefix()) == sent.addr()
+}
@@ -0,0 +1,147 @@
+//! Intercambio DNS "crudo" (un `Message` in, un `Message` out) contra un
+//! servidor puntual, sin pasar por el cache ni la lógica de TokioResolver.
+
+use anyhow::Context;
+use hickory_proto::op::Message;
+#[cfg(feature = "dnssec")]
+use hickory_proto::op::{MessageType, OpCode, Query, ResponseCode};
+#[cfg(feature = "dnssec")]
+use hickory_proto::rr::{Name, Record, RecordType};
+use hickory_proto::runtime::{TokioRuntimeProvider, TokioTime};
+use hickory_proto::tcp::TcpClientStream;
+use hickory_proto::udp::UdpClientStream;
+use hickory_proto::xfer::{
+    DnsExchange, DnsHandle, DnsMultiplexer, DnsRequest, DnsRequestOptions, FirstAnswer,
+};
+
+use crate::padding;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Envía `msg` por UDP y, si la respuesta viene truncada, reintenta por TCP.
+pub async fn query(addr: SocketAddr, msg: Message, timeout: Duration) -> anyhow::Result<Message> {
+    query_with(addr, msg, timeout, DnsRequestOptions::default(), None).await
+}
+
+/// Como `query`, con opciones de request (p.ej. `case_randomization`, que
+/// hickory aplica y verifica sólo sobre UDP) y el bloque de padding para el
+/// reintento por TCP (RFC 7830; UDP nunca se rellena).
+pub async fn query_with(
+    addr: SocketAddr,
+    msg: Message,
+    timeout: Duration,
+    opts: DnsRequestOptions,
+    tcp_padding: Option<u16>,
+) -> anyhow::Result<Message> {
+    let resp = query_udp(addr, msg.clone(), timeout, opts).await?;
+    if !resp.truncated() {
+        return Ok(resp);
+    }
+    tracing::debug!("respuesta truncada de {addr}, reintento por TCP");
+    let mut msg = msg;
+    if let Some(block) = tcp_padding {
+        padding::pad_message(&mut msg, block)?;
+    }
+    query_tcp(addr, msg, timeout, opts).await
+}
+
+pub async fn query_udp(
+    addr: SocketAddr,
+    msg: Message,
+    timeout: Duration,
+    opts: DnsRequestOptions,
+) -> anyhow::Result<Message> {
+    let stream = UdpClientStream::builder(addr, TokioRuntimeProvider::new())
+        .with_timeout(Some(timeout))
+        .build();
+    let (exchange, bg) = DnsExchange::connect::<_, _, TokioTime>(stream)
+        .await
+        .with_context(|| format!("conectando UDP a {addr}"))?;
+    tokio::spawn(bg);
+
+    send(&exchange, msg, opts)
+        .await
+        .with_context(|| format!("consulta UDP a {addr}"))
+}
+
+pub async fn query_tcp(
+    addr: SocketAddr,
+    msg: Message,
+    timeout: Duration,
+    opts: DnsRequestOptions,
+) -> anyhow::Result<Message> {
+    let (connect, handle) =
+        TcpClientStream::new(addr, None, Some(timeout), TokioRuntimeProvider::new());
+    let multiplexer = DnsMultiplexer::with_timeout(connect, handle, timeout, None);
+    let (exchange, bg) = DnsExchange::connect::<_, _, TokioTime>(multiplexer)
+        .await
+        .with_context(|| format!("conectando TCP a {addr}"))?;
+    tokio::spawn(bg);
+
+    send(&exchange, msg, opts)
+        .await
+        .with_context(|| format!("consulta TCP a {addr}"))
+}
+
+async fn send(
+    exchange: &DnsExchange,
+    msg: Message,
+    opts: DnsRequestOptions,
+) -> anyhow::Result<Message> {
+    let resp = exchange
+        .send(DnsRequest::new(msg, opts))
+        .first_answer()
+        .await?;
+    Ok(resp.into_message())
+}
+
+/// Transferencia de zona (AXFR, RFC 5936) por TCP. Devuelve los registros
+/// con la SOA inicial y sin la que cierra la transferencia.
+#[cfg(feature = "dnssec")]
+pub async fn axfr(addr: SocketAddr, zone: Name, timeout: Duration) -> anyhow::Result<Vec<Record>> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    let mut msg = Message::new();
+    msg.set_id(rand::random())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query);
+    msg.add_query(Query::query(zone, RecordType::AXFR));
+    let bytes = msg.to_vec()?;
+
+    let transfer = async {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(&(bytes.len() as u16).to_be_bytes()).await?;
+        stream.write_all(&bytes).await?;
+
+        let mut records = Vec::new();
+        let mut soas = 0;
+        loop {
+            let len = stream.read_u16().await? as usize;
+            let mut buf = vec![0; len];
+            stream.read_exact(&mut buf).await?;
+            let mut resp = Message::from_vec(&buf)?;
+            if resp.id() != msg.id() {
+                anyhow::bail!("respuesta con otro id");
+            }
+            if resp.response_code() != ResponseCode::NoError {
+                anyhow::bail!("rcode {}", resp.response_code());
+            }
+            for r in resp.take_answers() {
+                if r.record_type() == RecordType::SOA {
+                    soas += 1;
+                    if soas == 2 {
+                        return Ok(records);
+                    }
+                }
+                records.push(r);
+            }
+        }
+    };
+    tokio::time::timeout(timeout, transfer)
+        .await
+        .map_err(|_| anyhow::anyhow!("timeout"))
+        .and_then(|r| r)
+        .with_context(|| format!("AXFR desde {addr}"))
+}
@@ -1,32 +1,282 @@
+use crate::config::{ForwarderConfig, HealthConfig, ResolutionConfig};
+use crate::consistency::{self, Consistency, Verdict};
+use crate::ecs::{self, Ecs};
+use crate::exchange;
+use crate::padding::Padding;
+use crate::retry::RetryPolicy;
+use crate::upstream::{Breaker, BreakerPolicy, Outcome, Upstream, UpstreamStats};
+use crate::validation::{Rejection, Validator};
 use anyhow::Context;
-use hickory_proto::xfer::Protocol;
-use hickory_resolver::TokioResolver;
+use hickory_proto::op::{Edns, Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::rdata::opt::{ClientSubnet, EdnsOption};
+use hickory_proto::rr::{Name, RecordType};
+use hickory_proto::xfer::{DnsRequestOptions, Protocol};
+use hickory_proto::{ProtoError, ProtoErrorKind};
+use hickory_resolver::lookup::Lookup;
 use hickory_resolver::name_server::TokioConnectionProvider;
-use hickory_resolver::config::{
-    NameServerConfig, NameServerConfigGroup, ResolverConfig, ResolverOpts,
-};
+use hickory_resolver::config::{LookupIpStrategy, NameServerConfig, ResolverConfig, ResolverOpts};
+use hickory_resolver::{ResolveError, TokioResolver};
+use rand::Rng;
 
+use futures_util::stream::{FuturesUnordered, StreamExt};
+
+use std::fmt;
+use std::future::Future;
 use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Cómo se reparte el tráfico entre upstreams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Orden estricto: el primero es primario, el resto backups.
+    Ordered,
+    /// Rota el upstream inicial en cada consulta.
+    RoundRobin,
+    /// Aleatorio ponderado por `weights`.
+    Random,
+    /// El de menor SRTT primero (los no medidos se prueban antes).
+    LowestSrtt,
+}
+
+impl Strategy {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let x = s.trim().to_ascii_lowercase();
+        match x.as_str() {
+            "ordered" | "strict" => Ok(Self::Ordered),
+            "round_robin" | "roundrobin" => Ok(Self::RoundRobin),
+            "random" | "weighted" => Ok(Self::Random),
+            "lowest_srtt" | "srtt" => Ok(Self::LowestSrtt),
+            _ => anyhow::bail!("forwarder.strategy debe ser: ordered | round_robin | random | lowest_srtt"),
+        }
+    }
+
+    /// Orden de consulta de los upstreams (índices). `turn` es el contador de
+    /// `round_robin`; `weights` y `srtts` van por upstream.
+    pub fn order<R: Rng + ?Sized>(
+        self,
+        weights: &[u32],
+        srtts: &[Option<Duration>],
+        turn: usize,
+        rng: &mut R,
+    ) -> Vec<usize> {
+        let n = weights.len();
+        match self {
+            Self::Ordered => (0..n).collect(),
+            Self::RoundRobin => {
+                let start = turn % n.max(1);
+                (0..n).map(|i| (start + i) % n).collect()
+            }
+            Self::Random => weighted_shuffle(weights, rng),
+            Self::LowestSrtt => {
+                let mut idx: Vec<usize> = (0..n).collect();
+                idx.sort_by_key(|&i| srtts.get(i).copied().flatten().unwrap_or(Duration::ZERO));
+                idx
+            }
+        }
+    }
+}
+
+/// Forwarder con un resolver por upstream, para poder elegir el orden de
+/// consulta y llevar estadísticas por upstream.
+#[derive(Clone)]
+pub struct Forwarder {
+    upstreams: Arc<[Upstream]>,
+    strategy: Strategy,
+    next: Arc<AtomicUsize>,
+    policy: BreakerPolicy,
+    probe: Option<Probe>,
+    hedge: Option<Hedge>,
+    hedge_stats: Arc<HedgeStats>,
+    /// Reenviar el mensaje crudo en vez de pasar por `TokioResolver::lookup`.
+    passthrough: bool,
+    /// Timeout por consulta del camino pass-through.
+    timeout: Duration,
+    case_randomization: bool,
+    validator: Option<Validator>,
+    consistency: Option<Arc<Consistency>>,
+    ecs: Option<Arc<Ecs>>,
+    /// Reintentos y deadline de toda la consulta (ver `[resolution]`).
+    retry: RetryPolicy,
+    /// Bloque de padding de las consultas pass-through que van por TCP.
+    query_padding: Option<u16>,
+}
+
+/// Mínimo de muestras para confiar en el p95 del histograma.
+const HEDGE_P95_MIN_SAMPLES: u64 = 20;
+
+/// Request hedging: tras `delay` sin respuesta se lanza la misma consulta al
+/// siguiente upstream y se queda la primera respuesta válida.
+#[derive(Debug, Clone, Copy)]
+struct Hedge {
+    delay: Duration,
+    p95: bool,
+}
+
+impl Hedge {
+    fn from_config(cfg: &ForwarderConfig) -> Option<Self> {
+        (cfg.hedge_delay_ms > 0).then(|| Self {
+            delay: Duration::from_millis(cfg.hedge_delay_ms),
+            p95: cfg.hedge_p95,
+        })
+    }
+
+    /// Delay para `up`: su p95 (si hay muestras suficientes) sin pasar del configurado.
+    fn delay_for(&self, up: &Upstream) -> Duration {
+        if !self.p95 {
+            return self.delay;
+        }
+        up.stats
+            .latency_quantile(0.95, HEDGE_P95_MIN_SAMPLES)
+            .map_or(self.delay, |p95| p95.min(self.delay))
+    }
+}
+
+#[derive(Default)]
+struct HedgeStats {
+    /// Consultas en las que se lanzó el hedge.
+    launched: AtomicU64,
+    /// ... y ganó la consulta hedge.
+    hedge_wins: AtomicU64,
+    /// ... y ganó igual el upstream original.
+    primary_wins: AtomicU64,
+}
+
+/// Health check activo: consulta fija enviada a cada upstream sin cache.
+#[derive(Clone)]
+struct Probe {
+    query: Query,
+    interval: Duration,
+    timeout: Duration,
+}
 
-pub async fn build_forwarder(upstreams: &[String]) -> anyhow::Result<TokioResolver> {
+impl Probe {
+    fn from_config(cfg: &HealthConfig) -> anyhow::Result<Option<Self>> {
+        if !cfg.enabled {
+            return Ok(None);
+        }
+        let name = Name::from_ascii(&cfg.probe_name)
+            .with_context(|| format!("forwarder.health.probe_name inválido: {}", cfg.probe_name))?;
+        let qtype = RecordType::from_str(&cfg.probe_qtype.to_ascii_uppercase())
+            .with_context(|| format!("forwarder.health.probe_qtype inválido: {}", cfg.probe_qtype))?;
 
-    let mut group = NameServerConfigGroup::new();
+        Ok(Some(Self {
+            query: Query::query(name, qtype),
+            interval: Duration::from_secs(cfg.interval_secs.max(1)),
+            timeout: Duration::from_millis(cfg.timeout_ms),
+        }))
+    }
+
+    fn message(&self) -> Message {
+        let mut m = Message::new();
+        m.set_message_type(MessageType::Query);
+        m.set_op_code(OpCode::Query);
+        m.set_recursion_desired(true);
+        m.add_query(self.query.clone());
+        m
+    }
+}
 
+pub async fn build_forwarder(
+    upstreams: &[String],
+    cfg: &ForwarderConfig,
+    resolution: &ResolutionConfig,
+) -> anyhow::Result<Forwarder> {
+    let strategy = Strategy::parse(&cfg.strategy)?;
+    let opts = resolver_opts(cfg)?;
+    if cfg.attempts.is_some() {
+        tracing::warn!("forwarder.attempts está obsoleto: los reintentos los define [resolution]");
+    }
+
+    let mut built = Vec::with_capacity(upstreams.len());
     for u in upstreams {
         let addr: SocketAddr = u.parse().with_context(|| format!("upstream inválido: {u}"))?;
+        let weight = cfg.weights.get(u).copied().unwrap_or(1);
 
-        group.push(NameServerConfig {
-            socket_addr: addr,
-            protocol: Protocol::Udp,
-            tls_dns_name: None,
-            trust_negative_responses: true,
-            bind_addr: None,
-            http_endpoint: None,
+        built.push(Upstream {
+            addr,
+            weight,
+            resolver: build_resolver(addr, opts.clone()),
+            stats: UpstreamStats::default(),
+            breaker: Breaker::default(),
         });
+    }
 
-        group.push(NameServerConfig {
+    if built.is_empty() {
+        anyhow::bail!("forwarder sin upstreams");
+    }
+
+    let ecs = Ecs::from_config(&cfg.ecs)?;
+    if ecs.is_some() && !cfg.passthrough {
+        anyhow::bail!("forwarder.ecs requiere forwarder.passthrough = true");
+    }
+
+    let h = &cfg.health;
+    Ok(Forwarder {
+        upstreams: built.into(),
+        strategy,
+        next: Arc::new(AtomicUsize::new(0)),
+        policy: BreakerPolicy {
+            failure_threshold: h.failure_threshold,
+            backoff_base: Duration::from_secs(h.backoff_base_secs),
+            backoff_max: Duration::from_secs(h.backoff_max_secs),
+            jitter: h.jitter,
+        },
+        probe: Probe::from_config(h)?,
+        hedge: Hedge::from_config(cfg),
+        hedge_stats: Arc::new(HedgeStats::default()),
+        passthrough: cfg.passthrough,
+        timeout: Duration::from_millis(cfg.timeout_ms),
+        case_randomization: cfg.case_randomization,
+        validator: cfg
+            .validation
+            .enabled
+            .then(|| Validator::new(cfg.validation.max_ttl_secs)),
+        consistency: Consistency::from_config(&cfg.consistency)?.map(Arc::new),
+        ecs: ecs.map(Arc::new),
+        retry: RetryPolicy::from_config(resolution),
+        query_padding: None,
+    })
+}
+
+/// `ResolverOpts` de hickory para cada upstream, desde `[forwarder]`.
+pub fn resolver_opts(cfg: &ForwarderConfig) -> anyhow::Result<ResolverOpts> {
+    let mut opts = ResolverOpts::default();
+    opts.timeout = Duration::from_millis(cfg.timeout_ms);
+    // Un intento por upstream: los reintentos son de `RetryPolicy`.
+    opts.attempts = 1;
+    opts.edns0 = cfg.edns0;
+    opts.ip_strategy = parse_ip_strategy(&cfg.ip_strategy)?;
+    // 0 = sin cache interno (moka con capacidad 0 no guarda nada).
+    opts.cache_size = cfg.cache_size;
+    opts.num_concurrent_reqs = cfg.num_concurrent_reqs;
+    opts.case_randomization = cfg.case_randomization;
+    Ok(opts)
+}
+
+fn parse_ip_strategy(s: &str) -> anyhow::Result<LookupIpStrategy> {
+    let x = s.trim().to_ascii_lowercase();
+    match x.as_str() {
+        "ipv4_only" | "ipv4" => Ok(LookupIpStrategy::Ipv4Only),
+        "ipv6_only" | "ipv6" => Ok(LookupIpStrategy::Ipv6Only),
+        "ipv4_and_ipv6" | "both" => Ok(LookupIpStrategy::Ipv4AndIpv6),
+        "ipv6_then_ipv4" => Ok(LookupIpStrategy::Ipv6thenIpv4),
+        "ipv4_then_ipv6" => Ok(LookupIpStrategy::Ipv4thenIpv6),
+        _ => anyhow::bail!(
+            "forwarder.ip_strategy debe ser: ipv4_only | ipv6_only | ipv4_and_ipv6 | ipv6_then_ipv4 | ipv4_then_ipv6"
+        ),
+    }
+}
+
+fn build_resolver(addr: SocketAddr, opts: ResolverOpts) -> TokioResolver {
+    let mut cfg = ResolverConfig::new();
+
+    for protocol in [Protocol::Udp, Protocol::Tcp] {
+        cfg.add_name_server(NameServerConfig {
             socket_addr: addr,
-            protocol: Protocol::Tcp,
+            protocol,
             tls_dns_name: None,
             trust_negative_responses: true,
             bind_addr: None,
@@ -34,19 +284,550 @@ pub async fn build_forwarder(upstreams: &[String]) -> anyhow::Result<TokioResolv
         });
     }
 
-    let mut cfg = ResolverConfig::new();
-    for ns in group.iter().cloned() {
-        cfg.add_name_server(ns);
+    TokioResolver::builder_with_config(cfg, TokioConnectionProvider::default())
+        .with_options(opts)
+        .build()
+}
+
+impl Forwarder {
+    /// Padding de las consultas pass-through según `[padding.queries]`. Los
+    /// upstreams son UDP/TCP plano: sólo aplica el bloque de `tcp`.
+    pub fn with_query_padding(mut self, padding: &Padding) -> Self {
+        self.query_padding = padding.query_block(Protocol::Tcp);
+        self
     }
 
+    /// Consulta los upstreams en el orden de la estrategia. Se pasa al
+    /// siguiente ante error/timeout/SERVFAIL; NXDOMAIN y NODATA son finales.
+    pub async fn lookup<'a>(&'a self, name: Name, qtype: RecordType) -> Result<Lookup, ResolveError> {
+        let what = format!("{name} {qtype:?}");
+        let attempt = |up: &'a Upstream| {
+            let name = name.clone();
+            async move {
+                let lookup = up.resolver.lookup(name.clone(), qtype).await?;
+                if let Some(v) = &self.validator {
+                    if let Err(why) = v.check_records(&name, lookup.records()) {
+                        self.rejected(up, &why);
+                        return Err(ResolveError::from(format!("respuesta rechazada: {why}")));
+                    }
+                }
+                Ok(lookup)
+            }
+        };
 
-    let opts = ResolverOpts::default();
+        let consistency = self.consistency_for(&name);
+        let once = || {
+            let (what, attempt) = (&what, &attempt);
+            async move {
+                match consistency {
+                    Some(c) => {
+                        self.cross_check(c, what, attempt, outcome_of, lookup_fingerprint, ResolveError::from)
+                            .await
+                    }
+                    None => self.dispatch(what, attempt, outcome_of).await,
+                }
+            }
+        };
+        // El veredicto de consistencia no se reintenta: ya consultó a varios.
+        let retryable = |res: &Option<Result<Lookup, ResolveError>>| {
+            consistency.is_none() && res.as_ref().is_some_and(|r| outcome_of(r) != Outcome::Ok)
+        };
+        let expired = || Some(Err(ResolveError::from(ProtoError::from(ProtoErrorKind::Timeout))));
 
-    let resolver = TokioResolver::builder_with_config(cfg, TokioConnectionProvider::default())
-        .with_options(opts)
-        .build();
+        let res = self.retry.run(once, retryable, expired).await;
+        res.unwrap_or_else(|| Err(ResolveError::from("forwarder sin upstreams")))
+    }
+
+    /// Pass-through: reenvía `query` tal cual (EDNS, CD/DO incluidos) y
+    /// devuelve el `Message` del upstream sin aplanar. Mismo orden, failover
+    /// y hedging que `lookup`; si todos fallan se devuelve la última
+    /// respuesta (p.ej. un SERVFAIL del upstream) o el último error.
+    ///
+    /// `ecs` es la subred a agregar (ver `ecs::Ecs::source_for`); sólo se
+    /// manda a los upstreams permitidos.
+    pub async fn forward<'a>(
+        &'a self,
+        query: &'a Message,
+        ecs: Option<ClientSubnet>,
+    ) -> anyhow::Result<Message> {
+        let what = query
+            .queries()
+            .first()
+            .map(|q| format!("{} {:?}", q.name(), q.query_type()))
+            .unwrap_or_default();
+        let mut opts = DnsRequestOptions::default();
+        opts.case_randomization = self.case_randomization;
+        let sent = query.queries().first();
+
+        let attempt = |up: &'a Upstream| async move {
+            let mut msg = query.clone();
+            let subnet = ecs.filter(|_| self.ecs.as_ref().is_some_and(|e| e.applies_to_upstream(&up.addr)));
+            if let Some(subnet) = subnet {
+                let edns = msg.extensions_mut().get_or_insert_with(Edns::new);
+                edns.options_mut().insert(EdnsOption::Subnet(subnet));
+            }
+
+            let resp = exchange::query_with(up.addr, msg, self.timeout, opts, self.query_padding).await?;
+            if let (Some(sent), Some(got)) = (subnet, resp.extensions().as_ref().and_then(ecs::subnet_of)) {
+                if !ecs::response_matches(&sent, got) {
+                    up.stats.record_rejected();
+                    anyhow::bail!("upstream {}: ECS de la respuesta no coincide con la consulta", up.addr);
+                }
+            }
+            if let (Some(v), Some(sent)) = (&self.validator, sent) {
+                if let Err(why) = v.check_message(sent, &resp) {
+                    self.rejected(up, &why);
+                    return Err(anyhow::Error::new(why).context(format!("respuesta de {} rechazada", up.addr)));
+                }
+            }
+            Ok(resp)
+        };
+
+        let consistency = sent.and_then(|q| self.consistency_for(q.name()));
+        let once = || {
+            let (what, attempt) = (&what, &attempt);
+            async move {
+                match consistency {
+                    Some(c) => {
+                        self.cross_check(c, what, attempt, message_outcome, message_fingerprint, |m| {
+                            anyhow::anyhow!(m)
+                        })
+                        .await
+                    }
+                    None => self.dispatch(what, attempt, message_outcome).await,
+                }
+            }
+        };
+        let retryable = |res: &Option<anyhow::Result<Message>>| {
+            consistency.is_none() && res.as_ref().is_some_and(|r| message_outcome(r) != Outcome::Ok)
+        };
+        let expired = || Some(Err(anyhow::Error::new(ProtoError::from(ProtoErrorKind::Timeout))));
+
+        let res = self.retry.run(once, retryable, expired).await;
+        res.unwrap_or_else(|| Err(anyhow::anyhow!("forwarder sin upstreams")))
+    }
+
+    pub fn passthrough(&self) -> bool {
+        self.passthrough
+    }
+
+    pub fn ecs(&self) -> Option<&Ecs> {
+        self.ecs.as_deref()
+    }
+
+    fn rejected(&self, up: &Upstream, why: &Rejection) {
+        up.stats.record_rejected();
+        tracing::warn!("upstream {}: respuesta rechazada ({why})", up.addr);
+    }
+
+    /// Núcleo común de `lookup`/`forward`: recorre `order()` con failover y,
+    /// si está habilitado, hedging. `outcome` clasifica cada resultado; sólo
+    /// `Outcome::Ok` corta la búsqueda.
+    ///
+    /// Con hedging, si el upstream en curso no contesta en el delay se lanza
+    /// en paralelo el siguiente (una sola vez por consulta); la primera
+    /// respuesta válida gana y la otra consulta se cancela al soltarla.
+    async fn dispatch<'a, T, E, F, Fut>(
+        &'a self,
+        what: &str,
+        attempt: F,
+        outcome: fn(&Result<T, E>) -> Outcome,
+    ) -> Option<Result<T, E>>
+    where
+        F: Fn(&'a Upstream) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let run = |i: usize, is_hedge: bool| {
+            let fut = attempt(&self.upstreams[i]);
+            async move {
+                let started = Instant::now();
+                let res = fut.await;
+                (i, is_hedge, res, started.elapsed())
+            }
+        };
+
+        let mut order = self.order().into_iter();
+        let mut inflight = FuturesUnordered::new();
+        let mut last = None;
+        let mut hedged = false;
+
+        let first = order.next()?;
+        let mut hedge_at = self.hedge_deadline(first);
+        inflight.push(run(first, false));
 
-    Ok(resolver)
+        while !inflight.is_empty() {
+            let timer = async {
+                match hedge_at {
+                    Some(at) if !hedged => tokio::time::sleep_until(at.into()).await,
+                    _ => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                Some((i, is_hedge, res, elapsed)) = inflight.next() => {
+                    let up = &self.upstreams[i];
+                    let out = outcome(&res);
+                    self.record(up, out, elapsed);
+
+                    if out == Outcome::Ok {
+                        if hedged {
+                            self.hedge_stats.record_win(is_hedge);
+                        }
+                        return Some(res);
+                    }
+
+                    tracing::debug!("upstream {} falló para {} ({:?})", up.addr, what, out);
+                    last = Some(res);
+                    if inflight.is_empty() {
+                        if let Some(j) = order.next() {
+                            hedge_at = self.hedge_deadline(j);
+                            inflight.push(run(j, false));
+                        }
+                    }
+                }
+                _ = timer => {
+                    hedged = true;
+                    if let Some(j) = order.next() {
+                        tracing::debug!("hedge: {} también a {}", what, self.upstreams[j].addr);
+                        self.hedge_stats.launched.fetch_add(1, Ordering::Relaxed);
+                        inflight.push(run(j, true));
+                    }
+                }
+            }
+        }
+
+        last
+    }
+
+    fn consistency_for(&self, name: &Name) -> Option<&Consistency> {
+        self.consistency.as_deref().filter(|c| c.applies(name))
+    }
+
+    /// Modo consistencia: consulta en paralelo a los primeros `fanout`
+    /// upstreams, compara las respuestas válidas y elige según la política.
+    /// Sin hedging ni failover: si todos fallan se devuelve el último error.
+    async fn cross_check<'a, T, E, F, Fut>(
+        &'a self,
+        c: &Consistency,
+        what: &str,
+        attempt: F,
+        outcome: fn(&Result<T, E>) -> Outcome,
+        fingerprint: fn(&Result<T, E>) -> Vec<String>,
+        servfail: fn(String) -> E,
+    ) -> Option<Result<T, E>>
+    where
+        F: Fn(&'a Upstream) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let picked: Vec<usize> = self.order().into_iter().take(c.policy().fanout()).collect();
+        if picked.len() < 2 {
+            return self.dispatch(what, attempt, outcome).await;
+        }
+
+        let runs = picked.iter().map(|&i| {
+            let fut = attempt(&self.upstreams[i]);
+            async move {
+                let started = Instant::now();
+                let res = fut.await;
+                (i, res, started.elapsed())
+            }
+        });
+
+        let mut valid = Vec::new();
+        let mut last = None;
+        for (i, res, elapsed) in futures_util::future::join_all(runs).await {
+            let up = &self.upstreams[i];
+            let out = outcome(&res);
+            self.record(up, out, elapsed);
+            if out == Outcome::Ok {
+                valid.push((up.addr.to_string(), res));
+            } else {
+                last = Some(res);
+            }
+        }
+        if valid.is_empty() {
+            return last;
+        }
+
+        let fps: Vec<(String, Vec<String>)> = valid
+            .iter()
+            .map(|(addr, res)| (addr.clone(), fingerprint(res)))
+            .collect();
+
+        match c.decide(what, &fps) {
+            Verdict::Use(k) => Some(valid.swap_remove(k).1),
+            Verdict::ServFail => Some(Err(servfail(format!("respuestas inconsistentes para {what}")))),
+        }
+    }
+
+    fn hedge_deadline(&self, i: usize) -> Option<Instant> {
+        self.hedge
+            .map(|h| Instant::now() + h.delay_for(&self.upstreams[i]))
+    }
+
+    /// Índices de upstreams en el orden en que hay que probarlos, sin los
+    /// expulsados por el circuit breaker. Si están todos expulsados se usan
+    /// todos igual (fail-open): peor es no contestar nada.
+    fn order(&self) -> Vec<usize> {
+        let order = self.strategy_order();
+        let available: Vec<usize> = order
+            .iter()
+            .copied()
+            .filter(|&i| self.upstreams[i].breaker.is_available())
+            .collect();
+
+        if available.is_empty() {
+            tracing::debug!("todos los upstreams expulsados: se prueban igual");
+            order
+        } else {
+            available
+        }
+    }
+
+    fn strategy_order(&self) -> Vec<usize> {
+        let weights: Vec<u32> = self.upstreams.iter().map(|u| u.weight).collect();
+        let srtts: Vec<Option<Duration>> = match self.strategy {
+            Strategy::LowestSrtt => self.upstreams.iter().map(|u| u.stats.srtt()).collect(),
+            _ => Vec::new(),
+        };
+        let turn = match self.strategy {
+            Strategy::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            _ => 0,
+        };
+        self.strategy.order(&weights, &srtts, turn, &mut rand::rng())
+    }
+
+    /// Actualiza estadísticas y circuit breaker con el resultado de una consulta.
+    fn record(&self, up: &Upstream, outcome: Outcome, elapsed: Duration) {
+        up.stats.record(outcome, elapsed);
+        self.update_breaker(up, outcome, "tráfico");
+    }
+
+    fn update_breaker(&self, up: &Upstream, outcome: Outcome, origin: &str) {
+        match outcome {
+            Outcome::Ok => {
+                if up.breaker.on_success() {
+                    tracing::info!("upstream {} re-admitido ({origin})", up.addr);
+                }
+            }
+            Outcome::Error | Outcome::Timeout => {
+                if let Some(backoff) = up.breaker.on_failure(&self.policy) {
+                    tracing::warn!(
+                        "upstream {} expulsado por {:.1}s ({origin}: {:?})",
+                        up.addr,
+                        backoff.as_secs_f64(),
+                        outcome
+                    );
+                }
+            }
+        }
+    }
+
+    /// Una línea por upstream (estado de salud + estadísticas) y, si está
+    /// habilitado, una con el hedging.
+    pub fn status_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .upstreams
+            .iter()
+            .map(|up| {
+                format!(
+                    "upstream {} peso={} estado={} {}",
+                    up.addr,
+                    up.weight,
+                    up.breaker.status(),
+                    up.stats.snapshot()
+                )
+            })
+            .collect();
+
+        if let Some(h) = &self.hedge {
+            lines.push(format!(
+                "hedging delay={}ms p95={} {}",
+                h.delay.as_millis(),
+                h.p95,
+                self.hedge_stats
+            ));
+        }
+        if let Some(c) = &self.consistency {
+            lines.push(c.to_string());
+        }
+        lines
+    }
+
+    pub fn log_stats(&self) {
+        for line in self.status_lines() {
+            tracing::info!("{line}");
+        }
+    }
+
+    /// Lanza los health checks periódicos (si están habilitados).
+    pub fn spawn_health_checks(&self) {
+        let Some(probe) = self.probe.clone() else {
+            return;
+        };
+        let fwd = self.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(probe.interval);
+            loop {
+                tick.tick().await;
+                let probes = (0..fwd.upstreams.len()).map(|i| fwd.probe_one(i, &probe));
+                futures_util::future::join_all(probes).await;
+            }
+        });
+    }
+
+    async fn probe_one(&self, i: usize, probe: &Probe) {
+        let up = &self.upstreams[i];
+        let started = Instant::now();
+        let res = exchange::query(up.addr, probe.message(), probe.timeout).await;
+
+        let outcome = match &res {
+            Ok(resp) if matches!(resp.response_code(), ResponseCode::NoError | ResponseCode::NXDomain) => {
+                Outcome::Ok
+            }
+            Ok(resp) => {
+                tracing::debug!("probe a {}: rcode {}", up.addr, resp.response_code());
+                Outcome::Error
+            }
+            Err(e) => {
+                tracing::debug!("probe a {} falló: {e:#}", up.addr);
+                if started.elapsed() >= probe.timeout {
+                    Outcome::Timeout
+                } else {
+                    Outcome::Error
+                }
+            }
+        };
+
+        // Los probes no cuentan en las estadísticas de tráfico, sólo en el breaker.
+        self.update_breaker(up, outcome, "health check");
+    }
+
+    /// Loguea las estadísticas por upstream cada `every`.
+    pub fn spawn_stats_logger(&self, every: Duration) {
+        let fwd = self.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(every);
+            tick.tick().await;
+            loop {
+                tick.tick().await;
+                fwd.log_stats();
+            }
+        });
+    }
+}
+
+impl HedgeStats {
+    fn record_win(&self, is_hedge: bool) {
+        let counter = if is_hedge { &self.hedge_wins } else { &self.primary_wins };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Display for HedgeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let launched = self.launched.load(Ordering::Relaxed);
+        let hedge = self.hedge_wins.load(Ordering::Relaxed);
+        let primary = self.primary_wins.load(Ordering::Relaxed);
+        let decided = hedge + primary;
+        let rate = if decided == 0 {
+            0.0
+        } else {
+            hedge as f64 * 100.0 / decided as f64
+        };
+        write!(
+            f,
+            "lanzados={launched} gana_hedge={hedge} gana_original={primary} win_rate={rate:.1}%"
+        )
+    }
+}
+
+/// Permutación aleatoria donde cada upstream sale primero con probabilidad
+/// proporcional a su peso (muestreo sin reposición).
+pub fn weighted_shuffle<R: Rng + ?Sized>(weights: &[u32], rng: &mut R) -> Vec<usize> {
+    let mut pool: Vec<usize> = (0..weights.len()).collect();
+    let mut out = Vec::with_capacity(pool.len());
+
+    while !pool.is_empty() {
+        let total: u64 = pool.iter().map(|&i| weights[i] as u64).sum();
+        let pick = if total == 0 {
+            0
+        } else {
+            let mut r = rng.random_range(0..total);
+            pool.iter()
+                .position(|&i| {
+                    let w = weights[i] as u64;
+                    if r < w {
+                        true
+                    } else {
+                        r -= w;
+                        false
+                    }
+                })
+                .unwrap_or(0)
+        };
+        out.push(pool.remove(pick));
+    }
+
+    out
 }
 
+fn outcome_of(res: &Result<Lookup, ResolveError>) -> Outcome {
+    match res {
+        Ok(_) => Outcome::Ok,
+        Err(e) if is_final(e) => Outcome::Ok,
+        Err(e) if is_timeout(e) => Outcome::Timeout,
+        Err(_) => Outcome::Error,
+    }
+}
+
+fn lookup_fingerprint(res: &Result<Lookup, ResolveError>) -> Vec<String> {
+    match res {
+        Ok(lookup) => consistency::fingerprint(ResponseCode::NoError, lookup.records().iter()),
+        Err(e) => match e.proto().map(|p| p.kind()) {
+            Some(ProtoErrorKind::NoRecordsFound { response_code, .. }) => {
+                consistency::fingerprint(*response_code, std::iter::empty())
+            }
+            _ => vec![e.to_string()],
+        },
+    }
+}
 
+fn message_fingerprint(res: &anyhow::Result<Message>) -> Vec<String> {
+    match res {
+        Ok(m) => consistency::fingerprint(m.response_code(), m.answers().iter()),
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+/// Pass-through: NOERROR/NXDOMAIN son respuestas finales; SERVFAIL,
+/// REFUSED y compañía pasan al siguiente upstream.
+fn message_outcome(res: &anyhow::Result<Message>) -> Outcome {
+    match res {
+        Ok(m) if matches!(m.response_code(), ResponseCode::NoError | ResponseCode::NXDomain) => Outcome::Ok,
+        Ok(_) => Outcome::Error,
+        Err(e) if is_proto_timeout(e) => Outcome::Timeout,
+        Err(_) => Outcome::Error,
+    }
+}
+
+fn is_proto_timeout(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| {
+        c.downcast_ref::<ProtoError>()
+            .is_some_and(|p| matches!(p.kind(), ProtoErrorKind::Timeout))
+    })
+}
+
+/// NXDOMAIN / NODATA: el upstream contestó, no tiene sentido probar otro.
+fn is_final(e: &ResolveError) -> bool {
+    match e.proto().map(|p| p.kind()) {
+        Some(ProtoErrorKind::NoRecordsFound { response_code, .. }) => {
+            matches!(*response_code, ResponseCode::NXDomain | ResponseCode::NoError)
+        }
+        _ => false,
+    }
+}
+
+fn is_timeout(e: &ResolveError) -> bool {
+    matches!(e.proto().map(|p| p.kind()), Some(ProtoErrorKind::Timeout))
+}
@@ -1,36 +1,61 @@
 use crate::{
-    cache::{CacheKey, CacheState, CachedEntry, DnsCaches},
+    cache::{CacheKey, CacheState, CacheStats, CachedEntry, DnsCaches},
     config::AppConfig,
+    ecs::{self, ClientEcs},
     filters::Filters,
-    recursor_engine::RecursorEngine,
+    forwarder::Forwarder,
+    nta,
+    padding::{self, Padding},
+    recursor_engine::{RecursorEngine, Resolution, Security},
+    rrset_cache::{Assembled, Trust},
     zones::ZoneStore,
 };
 
-use hickory_proto::op::{Message, MessageType, OpCode, ResponseCode};
-use hickory_proto::rr::{Name, Record, RecordType};
+use hickory_proto::op::{Edns, Header, Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption};
+use hickory_proto::rr::rdata::TXT;
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
 use hickory_proto::serialize::binary::{BinDecodable, BinEncodable, BinEncoder};
 
 use hickory_server::authority::MessageResponseBuilder;
 use hickory_server::server::{Request, RequestHandler, ResponseHandler, ResponseInfo};
 
 use hickory_proto::ProtoErrorKind;
-use hickory_resolver::{ResolveErrorKind, TokioResolver};
+use hickory_resolver::ResolveErrorKind;
 
 use tokio::spawn;
-use tokio::time::sleep;
 
 use std::iter;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
+/// Nombre (clase CH) que devuelve el estado operativo del servidor.
+const STATUS_NAME: &str = "status.server.";
+
+/// Control de negative trust anchors (clase CH): `<dominio>.nta-add.server.`
+/// y `<dominio>.nta-del.server.`; responden con el estado.
+const NTA_ADD: &str = ".nta-add.server.";
+const NTA_DEL: &str = ".nta-del.server.";
+
+/// Vacía el cache (clase CH): front y, en modo recursor, el de hickory.
+const FLUSH_NAME: &str = "flush.server.";
+
+/// Payload UDP que anunciamos cuando respondemos con EDNS (DNS Flag Day 2020).
+const EDNS_MAX_PAYLOAD: u16 = 1232;
+
+/// Extended DNS Error (RFC 8914): código de opción y INFO-CODE "DNSSEC Bogus".
+const EDE_CODE: u16 = 15;
+const EDE_DNSSEC_BOGUS: u16 = 6;
+
 #[derive(Clone)]
 pub struct DnsHandler {
     pub cfg: AppConfig,
     zones: Arc<ZoneStore>,
     filters: Arc<Filters>,
     caches: Arc<DnsCaches>,
-    forwarder: Option<TokioResolver>,
+    padding: Arc<Padding>,
+    forwarder: Option<Forwarder>,
     recursor: Option<Arc<RecursorEngine>>,
 }
 
@@ -40,14 +65,16 @@ impl DnsHandler {
         zones: ZoneStore,
         filters: Filters,
         caches: DnsCaches,
-        forwarder: Option<TokioResolver>,
+        forwarder: Option<Forwarder>,
         recursor: Option<RecursorEngine>,
     ) -> Self {
+        let padding = Padding::from_config(&cfg.padding);
         Self {
             cfg,
             zones: Arc::new(zones),
             filters: Arc::new(filters),
             caches: Arc::new(caches),
+            padding: Arc::new(padding),
             forwarder,
             recursor: recursor.map(Arc::new),
         }
@@ -76,6 +103,25 @@ impl DnsHandler {
                 .to_ascii_lowercase(),
             qtype: query_type.into(),
             do_bit,
+            subnet: None,
+        }
+    }
+
+    /// ECS a mandar para esta consulta (sólo forwarder pass-through con
+    /// `[forwarder.ecs]` y dominio permitido).
+    fn ecs_source(&self, req: &Request, qname: &Name) -> Option<ClientSubnet> {
+        let fwd = self.forwarder.as_ref().filter(|f| f.passthrough())?;
+        let ecs = fwd.ecs().filter(|e| e.applies_to_domain(qname))?;
+        let client_opt = req.edns().and_then(ecs::subnet_of);
+        ecs.source_for(req.src().ip(), client_opt)
+    }
+
+    /// Clave de cache para una consulta con ECS según el último scope visto.
+    async fn ecs_key(&self, base: &CacheKey, src: &ClientSubnet) -> CacheKey {
+        let family = (base.clone(), src.addr().is_ipv6());
+        match self.caches.ecs_scope.get(&family).await {
+            Some(scope) => base.with_subnet(src.addr(), src.source_prefix(), scope),
+            None => base.clone(),
         }
     }
 
@@ -97,6 +143,80 @@ impl DnsHandler {
         header.set_authoritative(false);
     }
 
+    /// Líneas de estado del modo activo (forwarder / recursor).
+    fn status_lines(&self) -> Vec<String> {
+        let mut lines = vec![self.caches.status_line()];
+        if let Some(fwd) = &self.forwarder {
+            lines.push("modo=forwarder".to_string());
+            lines.extend(fwd.status_lines());
+        } else if let Some(rec) = &self.recursor {
+            lines.push("modo=recursor".to_string());
+            lines.extend(rec.status_lines());
+        }
+        lines
+    }
+
+    /// Alta/baja de un NTA si `qname` es un comando de control; `false` si no lo es.
+    fn nta_control(&self, qname: &str) -> bool {
+        let Some(rec) = &self.recursor else {
+            return false;
+        };
+        let (domain, add) = if let Some(d) = qname.strip_suffix(NTA_ADD) {
+            (d, true)
+        } else if let Some(d) = qname.strip_suffix(NTA_DEL) {
+            (d, false)
+        } else {
+            return false;
+        };
+
+        match nta::parse_domain(domain) {
+            Ok(d) if add => {
+                let lifetime = Duration::from_secs(self.cfg.recursor.nta_lifetime_secs);
+                rec.ntas().insert(d, lifetime);
+            }
+            Ok(d) => {
+                if rec.ntas().remove(&d) {
+                    tracing::info!("NTA {d}: quitado, se vuelve a validar");
+                }
+            }
+            Err(e) => tracing::debug!("NTA inválido {domain}: {e}"),
+        }
+        true
+    }
+
+    /// Vaciado completo del cache si `qname` es el comando; `false` si no lo es.
+    fn flush_control(&self, qname: &str) -> bool {
+        if qname != FLUSH_NAME {
+            return false;
+        }
+        self.caches.flush();
+        if let Some(rec) = &self.recursor {
+            if let Err(e) = rec.flush_cache() {
+                tracing::warn!("flush: no pude vaciar el cache del recursor: {e:#}");
+            }
+        }
+        tracing::info!("cache vaciado");
+        true
+    }
+
+    /// Un TXT (clase CH) por línea de estado; cada línea se parte en
+    /// character-strings de hasta 255 bytes.
+    fn status_records(&self, name: Name) -> Vec<Record> {
+        self.status_lines()
+            .into_iter()
+            .map(|line| {
+                let chunks = line
+                    .as_bytes()
+                    .chunks(255)
+                    .map(|c| String::from_utf8_lossy(c).into_owned())
+                    .collect();
+                let mut r = Record::from_rdata(name.clone(), 0, RData::TXT(TXT::new(chunks)));
+                r.set_dns_class(DNSClass::CH);
+                r
+            })
+            .collect()
+    }
+
     fn encode_message(msg: &Message) -> anyhow::Result<Vec<u8>> {
         let mut buf = Vec::with_capacity(512);
         let mut enc = BinEncoder::new(&mut buf);
@@ -104,7 +224,88 @@ impl DnsHandler {
         Ok(buf)
     }
 
+    /// EDNS para la respuesta (RFC 6891: sólo si el cliente mandó EDNS).
+    /// Se copian las opciones de `body` (p.ej. EDE del upstream) y, si el
+    /// transporte tiene política de padding y el cliente incluyó la opción
+    /// Padding (RFC 7830), se rellena.
+    fn response_edns(&self, req: &Request, header: &Header, body: &Message) -> Option<Edns> {
+        let req_edns = req.edns()?;
+
+        let mut edns = Edns::new();
+        edns.set_max_payload(req_edns.max_payload().clamp(512, EDNS_MAX_PAYLOAD));
+        edns.set_dnssec_ok(req_edns.flags().dnssec_ok);
+        if let Some(up) = body.extensions() {
+            let mut opts = up.options().clone();
+            opts.remove(EdnsCode::Padding);
+            *edns.options_mut() = opts;
+        }
+
+        let block = match self.padding.response_block(req.protocol()) {
+            Some(b) if padding::has_padding(req_edns) => b,
+            _ => return Some(edns),
+        };
+
+        // Emitimos la misma respuesta (sin padding) para medir su largo exacto.
+        let mut probe = MessageResponseBuilder::from_message_request(req);
+        probe.edns(edns.clone());
+        let msg = probe.build(
+            *header,
+            body.answers().iter(),
+            body.name_servers().iter(),
+            iter::empty(),
+            body.additionals().iter(),
+        );
+
+        let mut buf = Vec::with_capacity(512);
+        if msg.destructive_emit(&mut BinEncoder::new(&mut buf)).is_err() {
+            return Some(edns);
+        }
+
+        padding::pad_edns(&mut edns, buf.len(), block);
+        Some(edns)
+    }
+
+    async fn send_records<R: ResponseHandler>(
+        &self,
+        req: &Request,
+        response: &mut R,
+        header: Header,
+        answers: &[Record],
+    ) -> ResponseInfo {
+        let mut body = Message::new();
+        body.add_answers(answers.iter().cloned());
+        self.send_message(req, response, header, &body).await
+    }
+
+    /// Envía `header` + las secciones (y opciones EDNS) de `body`.
+    async fn send_message<R: ResponseHandler>(
+        &self,
+        req: &Request,
+        response: &mut R,
+        header: Header,
+        body: &Message,
+    ) -> ResponseInfo {
+        let mut builder = MessageResponseBuilder::from_message_request(req);
+        if let Some(edns) = self.response_edns(req, &header, body) {
+            builder.edns(edns);
+        }
+
+        let msg = builder.build(
+            header,
+            body.answers().iter(),
+            body.name_servers().iter(),
+            iter::empty(),
+            body.additionals().iter(),
+        );
+
+        response
+            .send_response(msg)
+            .await
+            .unwrap_or_else(|_| ResponseInfo::from(*req.header()))
+    }
+
     async fn send_cached_bytes<R: ResponseHandler>(
+        &self,
         req: &Request,
         response: &mut R,
         bytes: &[u8],
@@ -112,38 +313,198 @@ impl DnsHandler {
         let cached = Message::from_bytes(bytes).ok()?;
         let mut header = *req.header();
         Self::set_common_flags(req, &mut header, cached.response_code());
+        header.set_authentic_data(cached.authentic_data() && Self::wants_ad(req));
 
-        let msg = MessageResponseBuilder::from_message_request(req).build(
-            header,
-            cached.answers().iter(),
-            iter::empty(),
-            iter::empty(),
-            iter::empty(),
-        );
+        Some(self.send_message(req, response, header, &cached).await)
+    }
 
-        Some(
-            response
-                .send_response(msg)
-                .await
-                .unwrap_or_else(|_| ResponseInfo::from(*req.header())),
-        )
+    /// Respuesta armada desde el cache de RRsets; AD sólo si toda la cadena
+    /// está validada.
+    async fn send_assembled<R: ResponseHandler>(
+        &self,
+        req: &Request,
+        response: &mut R,
+        hit: &Assembled,
+    ) -> ResponseInfo {
+        let mut header = *req.header();
+        Self::set_common_flags(req, &mut header, ResponseCode::NoError);
+        header.set_authentic_data(hit.trust == Trust::Secure && Self::wants_ad(req));
+        self.send_records(req, response, header, &hit.answers).await
+    }
+
+    /// RFC 6840 §5.8: AD sólo si el cliente lo pidió (AD=1) o mandó DO=1.
+    fn wants_ad(req: &Request) -> bool {
+        req.header().authentic_data() || req.edns().is_some_and(|e| e.flags().dnssec_ok)
+    }
+
+    /// Consulta a reenviar en modo pass-through: la pregunta original (con su
+    /// capitalización), CD del cliente y sus opciones EDNS salvo Padding.
+    fn upstream_query(query: Query, client_edns: Option<&Edns>, checking_disabled: bool) -> Message {
+        let mut m = Message::new();
+        m.set_message_type(MessageType::Query);
+        m.set_op_code(OpCode::Query);
+        m.set_recursion_desired(true);
+        m.set_checking_disabled(checking_disabled);
+        m.add_query(query);
+
+        if let Some(ce) = client_edns {
+            let mut edns = Edns::new();
+            edns.set_max_payload(EDNS_MAX_PAYLOAD);
+            edns.set_dnssec_ok(ce.flags().dnssec_ok);
+            // ECS del cliente nunca pasa tal cual: lo agrega el forwarder
+            // según `[forwarder.ecs]`.
+            let mut opts = ce.options().clone();
+            opts.remove(EdnsCode::Padding);
+            opts.remove(EdnsCode::Subnet);
+            *edns.options_mut() = opts;
+            m.set_edns(edns);
+        }
+        m
+    }
+
+    fn passthrough(&self) -> Option<&Forwarder> {
+        self.forwarder.as_ref().filter(|f| f.passthrough())
+    }
+
+    /// Camino pass-through del forwarder: reenvía la consulta y devuelve la
+    /// respuesta del upstream con ID/flags ajustados.
+    async fn forward_passthrough<R: ResponseHandler>(
+        &self,
+        fwd: &Forwarder,
+        req: &Request,
+        response: &mut R,
+        base_key: &CacheKey,
+        ecs_src: Option<ClientSubnet>,
+    ) -> ResponseInfo {
+        let Some(query) = req.queries().first() else {
+            return self.send_servfail(req, response).await;
+        };
+        let cd = req.header().checking_disabled();
+        let msg = Self::upstream_query(query.original().clone(), req.edns(), cd);
+
+        let mut resp = match fwd.forward(&msg, ecs_src).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!("pass-through {}: {e:#}", query.name());
+                return self.send_servfail(req, response).await;
+            }
+        };
+
+        let rcode = resp.response_code();
+        let mut header = *req.header();
+        Self::set_common_flags(req, &mut header, rcode);
+        header.set_authentic_data(resp.authentic_data() && Self::wants_ad(req));
+
+        // ECS: el scope devuelto define con qué subred se indexa en cache. La
+        // opción no se cachea ni se reenvía, salvo eco al cliente que la mandó
+        // en modo honour.
+        let mut key = base_key.clone();
+        let returned = resp.extensions_mut().as_mut().and_then(|e| {
+            let scope = ecs::subnet_of(e).map(|s| s.scope_prefix());
+            e.options_mut().remove(EdnsCode::Subnet);
+            scope
+        });
+        if let Some(src) = &ecs_src {
+            let scope = returned.unwrap_or(0);
+            let family = (base_key.clone(), src.addr().is_ipv6());
+            self.caches.ecs_scope.insert(family, scope).await;
+            key = base_key.with_subnet(src.addr(), src.source_prefix(), scope);
+        }
+
+        // Con CD=1 la respuesta no está validada: no la cacheamos.
+        if !cd {
+            if let Ok(bytes) = Self::encode_message(&resp) {
+                let secure = resp.authentic_data();
+                self.store(&key, rcode, resp.answers(), resp.name_servers(), secure, bytes).await;
+            }
+        }
+
+        let honour = fwd.ecs().is_some_and(|e| e.client_ecs() == ClientEcs::Honour);
+        if let (true, Some(client), Some(edns)) = (
+            honour,
+            req.edns().and_then(ecs::subnet_of),
+            resp.extensions_mut().as_mut(),
+        ) {
+            let scope = returned.unwrap_or(0).min(client.source_prefix());
+            let echo = ClientSubnet::new(client.addr(), client.source_prefix(), scope);
+            edns.options_mut().insert(EdnsOption::Subnet(echo));
+        }
+
+        self.send_message(req, response, header, &resp).await
+    }
+
+    async fn send_servfail<R: ResponseHandler>(&self, req: &Request, response: &mut R) -> ResponseInfo {
+        let mut header = *req.header();
+        Self::set_common_flags(req, &mut header, ResponseCode::ServFail);
+        self.send_records(req, response, header, &[]).await
+    }
+
+    /// Write-through: positivos a `rrsets`, NXDOMAIN/NODATA (y CNAME que
+    /// terminan en NODATA) a `negative`, con la política 2-hit si está activa.
+    /// El TTL negativo sale de la SOA de `authority` si la hay.
+    async fn store(
+        &self,
+        key: &CacheKey,
+        rcode: ResponseCode,
+        answers: &[Record],
+        authority: &[Record],
+        secure: bool,
+        bytes: Vec<u8>,
+    ) {
+        let neg = &self.caches.negative_cfg;
+        let negative = match rcode {
+            ResponseCode::NoError if key.qtype == u16::from(RecordType::ANY) => false,
+            ResponseCode::NoError if !answers.is_empty() => {
+                // Pass-through: armada desde RRsets perdería la authority y
+                // los EDE del upstream, así que los positivos no se cachean.
+                if self.passthrough().is_some() {
+                    return;
+                }
+                if self.caches.rrsets.insert_answer(key, answers, Trust::of(secure)).await {
+                    CacheStats::count(&self.caches.stats.inserts);
+                    return;
+                }
+                neg.cache_nodata
+            }
+            ResponseCode::NoError => neg.cache_nodata,
+            ResponseCode::NXDomain => neg.cache_nxdomain,
+            _ => false,
+        };
+        if !(negative && neg.enabled) {
+            return;
+        }
+
+        let ttl = self.caches.negative_ttl_for(authority);
+        if neg.two_hit {
+            if self.caches.negative.get(key).await.is_none() {
+                if self.caches.negative_probe.get(key).await.is_some() {
+                    let entry = CachedEntry::new(bytes, ttl);
+                    self.caches.negative.insert(key.clone(), entry).await;
+                    CacheStats::count(&self.caches.stats.inserts);
+                } else {
+                    self.caches.negative_probe.insert(key.clone(), 1).await;
+                }
+            }
+        } else {
+            let entry = CachedEntry::new(bytes, ttl);
+            self.caches.negative.insert(key.clone(), entry).await;
+            CacheStats::count(&self.caches.stats.inserts);
+        }
     }
 
     async fn refresh_answer_cache(
         caches: Arc<DnsCaches>,
-        forwarder: Option<TokioResolver>,
+        forwarder: Option<Forwarder>,
         recursor: Option<Arc<RecursorEngine>>,
         key: CacheKey,
         qname: Name,
         qtype: RecordType,
         do_bit: bool,
     ) -> anyhow::Result<()> {
+        let mut secure = false;
         let (records, rcode) = if let Some(fwd) = forwarder {
             match fwd.lookup(qname, qtype).await {
-                Ok(lookup) => (
-                    lookup.records().iter().cloned().collect::<Vec<Record>>(),
-                    ResponseCode::NoError,
-                ),
+                Ok(lookup) => (lookup.records().to_vec(), ResponseCode::NoError),
                 Err(e) => match e.kind() {
                     ResolveErrorKind::Proto(pe) => match pe.kind() {
                         ProtoErrorKind::NoRecordsFound { response_code, .. } => (vec![], *response_code),
@@ -153,57 +514,16 @@ impl DnsHandler {
                 },
             }
         } else if let Some(rec) = recursor {
-            let mut last_err = None;
-            let mut result = None;
-
-            for attempt in 0..3 {
-                match rec.resolve(qname.clone(), qtype, do_bit).await {
-                    Ok(lookup) => {
-                        result = Some((
-                            lookup.records().iter().cloned().collect::<Vec<Record>>(),
-                            ResponseCode::NoError,
-                        ));
-                        break;
-                    }
-                    Err(e) => {
-                        last_err = Some(e);
-                        if attempt < 2 {
-                            sleep(Duration::from_millis(100)).await;
-                        }
-                    }
-                }
-            }
-
-            match result {
-                Some(ok) => ok,
-                None => {
-                    let _ = last_err;
-                    (vec![], ResponseCode::ServFail)
-                }
-            }
+            let res = rec.resolve(qname.clone(), qtype, do_bit, false).await;
+            secure = res.security() == Security::Secure;
+            (res.answers().to_vec(), res.rcode())
         } else {
             (vec![], ResponseCode::ServFail)
         };
 
         // Conservador: refrescamos sólo positivos con answers.
-        if rcode == ResponseCode::NoError && !records.is_empty() {
-            let mut m = Message::new();
-            m.set_message_type(MessageType::Response);
-            m.set_op_code(OpCode::Query);
-            m.set_response_code(rcode);
-            m.set_recursion_available(true);
-            m.set_authentic_data(false);
-
-            for r in &records {
-                m.add_answer(r.clone());
-            }
-
-            let bytes = Self::encode_message(&m)?;
-
-            let ttl_secs = records.iter().map(|r| r.ttl() as u64).min().unwrap_or(30);
-            let ttl = caches.clamp_ttl(Duration::from_secs(ttl_secs));
-            let entry = CachedEntry::new(bytes, ttl, caches.stale_window());
-            caches.answers.insert(key, entry).await;
+        if rcode == ResponseCode::NoError && caches.rrsets.insert_answer(&key, &records, Trust::of(secure)).await {
+            CacheStats::count(&caches.stats.inserts);
         }
 
         Ok(())
@@ -235,6 +555,20 @@ impl RequestHandler for DnsHandler {
         let qname = query.name().clone();
         let qtype = query.query_type();
 
+        // 0) status operativo, control de NTAs y flush: CH TXT, sólo desde loopback
+        if query.query_class() == DNSClass::CH
+            && qtype == RecordType::TXT
+            && req.src().ip().is_loopback()
+            && (qname.to_ascii() == STATUS_NAME
+                || self.nta_control(&qname.to_ascii())
+                || self.flush_control(&qname.to_ascii()))
+        {
+            let mut header = *req.header();
+            Self::set_common_flags(req, &mut header, ResponseCode::NoError);
+            let recs = self.status_records(qname.clone().into());
+            return self.send_records(req, &mut response, header, &recs).await;
+        }
+
         // 0) filtro
         if !self.filters.domain_allowed(&qname.to_ascii()) {
             let msg = MessageResponseBuilder::from_message_request(req)
@@ -250,29 +584,31 @@ impl RequestHandler for DnsHandler {
             let mut header = *req.header();
             Self::set_common_flags(req, &mut header, ResponseCode::NoError);
 
-            let msg = MessageResponseBuilder::from_message_request(req)
-                .build(header, recs.iter(), iter::empty(), iter::empty(), iter::empty());
-
-            return response
-                .send_response(msg)
-                .await
-                .unwrap_or_else(|_| ResponseInfo::from(*req.header()));
+            return self.send_records(req, &mut response, header, &recs).await;
         }
 
-        // 2) cache (answers) con Prefetch / Stale-While-Revalidate
-        let key = Self::cache_key(&qname, qtype, do_bit);
+        // 2) cache de RRsets con Prefetch / Stale-While-Revalidate
+        let base_key = Self::cache_key(&qname, qtype, do_bit);
+        let ecs_src = self.ecs_source(req, &qname.clone().into());
+        let key = match &ecs_src {
+            Some(src) => self.ecs_key(&base_key, src).await,
+            None => base_key.clone(),
+        };
 
-        if let Some(entry) = self.caches.answers.get(&key).await {
-            match self.caches.classify(&entry) {
+        // El pass-through no usa el cache de RRsets (ver `store`).
+        let hit = match self.passthrough() {
+            Some(_) => None,
+            None => self.caches.rrsets.lookup(&key).await,
+        };
+        if let Some(hit) = hit {
+            match self.caches.classify(hit.expires_at, hit.stale_until) {
                 CacheState::Fresh => {
-                    if let Some(info) = Self::send_cached_bytes(req, &mut response, &entry.bytes).await {
-                        return info;
-                    }
-                    // Si falla el decode, caemos a resolver normal.
+                    CacheStats::count(&self.caches.stats.hits);
+                    return self.send_assembled(req, &mut response, &hit).await;
                 }
 
                 CacheState::NearExpiry | CacheState::Stale => {
-                    let info = Self::send_cached_bytes(req, &mut response, &entry.bytes).await;
+                    CacheStats::count(&self.caches.stats.stale_hits);
 
                     // Revalidación en background (prefetch / SWR)
                     let caches = self.caches.clone();
@@ -294,10 +630,7 @@ impl RequestHandler for DnsHandler {
                         .await;
                     });
 
-                    if let Some(info) = info {
-                        return info;
-                    }
-                    // Si falla decode, caemos a resolver normal.
+                    return self.send_assembled(req, &mut response, &hit).await;
                 }
 
                 CacheState::Dead => {
@@ -307,113 +640,84 @@ impl RequestHandler for DnsHandler {
         }
 
         // 3) cache negativo existente
-        if let Some(entry) = self.caches.negative.get(&key).await {
-            if let Some(info) = Self::send_cached_bytes(req, &mut response, &entry.bytes).await {
+        if let Some(entry) = self.caches.negative.get(&key).await.filter(CachedEntry::is_live) {
+            if let Some(info) = self.send_cached_bytes(req, &mut response, &entry.bytes).await {
+                CacheStats::count(&self.caches.stats.negative_hits);
                 return info;
             }
         }
+        CacheStats::count(&self.caches.stats.misses);
 
         // 4) resolver
-        let (records, rcode) = if let Some(fwd) = &self.forwarder {
-            match fwd.lookup(qname.clone(), qtype).await {
-                Ok(lookup) => (
-                    lookup.records().iter().cloned().collect::<Vec<Record>>(),
-                    ResponseCode::NoError,
-                ),
+        if let Some(fwd) = self.passthrough() {
+            return self.forward_passthrough(fwd, req, &mut response, &base_key, ecs_src).await;
+        }
+
+        let checking_disabled = req.header().checking_disabled();
+        let mut secure = false;
+        let mut bogus = false;
+        let (records, authority, rcode) = if let Some(fwd) = &self.forwarder {
+            match fwd.lookup(qname.clone().into(), qtype).await {
+                Ok(lookup) => (lookup.records().to_vec(), vec![], ResponseCode::NoError),
                 Err(e) => match e.kind() {
                     ResolveErrorKind::Proto(pe) => match pe.kind() {
-                        ProtoErrorKind::NoRecordsFound { response_code, .. } => (vec![], *response_code),
-                        _ => (vec![], ResponseCode::ServFail),
+                        ProtoErrorKind::NoRecordsFound { response_code, .. } => (vec![], vec![], *response_code),
+                        _ => (vec![], vec![], ResponseCode::ServFail),
                     },
-                    _ => (vec![], ResponseCode::ServFail),
+                    _ => (vec![], vec![], ResponseCode::ServFail),
                 },
             }
         } else if let Some(rec) = &self.recursor {
             let name: Name = qname.clone().into();
 
-            // Reintento corto para evitar SERVFAIL transitorio por timeouts/red.
-            let mut last_err = None;
-            let mut result = None;
-
-            for attempt in 0..3 {
-                match rec.resolve(name.clone(), qtype, do_bit).await {
-                    Ok(lookup) => {
-                        result = Some((
-                            lookup.records().iter().cloned().collect::<Vec<Record>>(),
-                            ResponseCode::NoError,
-                        ));
-                        break;
-                    }
-                    Err(e) => {
-                        last_err = Some(e);
-                        if attempt < 2 {
-                            sleep(Duration::from_millis(100)).await;
-                        }
-                    }
-                }
-            }
-
-            match result {
-                Some(ok) => ok,
-                None => {
-                    let _ = last_err;
-                    (vec![], ResponseCode::ServFail)
+            // Reintentos y deadline: ver `[resolution]` (RetryPolicy).
+            let res = rec.resolve(name.clone(), qtype, do_bit, checking_disabled).await;
+            match &res {
+                Resolution::ServFail(why) => tracing::debug!("recursor {name} {qtype}: {why}"),
+                Resolution::Timeout => tracing::debug!("recursor {name} {qtype}: timeout"),
+                Resolution::Bogus(why) => {
+                    tracing::debug!("recursor {name} {qtype}: bogus: {why}");
+                    bogus = true;
                 }
+                _ => {}
             }
+            secure = res.security() == Security::Secure;
+            (res.answers().to_vec(), res.authority(), res.rcode())
         } else {
-            (vec![], ResponseCode::ServFail)
+            (vec![], vec![], ResponseCode::ServFail)
         };
 
         // construir respuesta final
         let mut header = *req.header();
         Self::set_common_flags(req, &mut header, rcode);
-
-        let msg = MessageResponseBuilder::from_message_request(req)
-            .build(header, records.iter(), iter::empty(), iter::empty(), iter::empty());
+        header.set_authentic_data(secure && Self::wants_ad(req));
 
         // --- write-through cache (positivo y negativo) ---
-        if let Ok(bytes) = {
-            let mut m = Message::new();
-            m.set_id(req.id());
-            m.set_message_type(MessageType::Response);
-            m.set_op_code(OpCode::Query);
-            m.set_response_code(rcode);
-            m.set_recursion_desired(req.recursion_desired());
-            m.set_recursion_available(true);
-            m.set_authentic_data(false);
-
-            for r in &records {
-                m.add_answer(r.clone());
-            }
-            Self::encode_message(&m)
-        } {
-            if rcode == ResponseCode::NoError && !records.is_empty() {
-                let ttl_secs = records.iter().map(|r| r.ttl() as u64).min().unwrap_or(30);
-                let ttl = self.caches.clamp_ttl(Duration::from_secs(ttl_secs));
-                let entry = CachedEntry::new(bytes.clone(), ttl, self.caches.stale_window());
-                self.caches.answers.insert(key.clone(), entry).await;
-            } else if rcode == ResponseCode::NXDomain && self.caches.negative_cfg.enabled && self.caches.negative_cfg.cache_nxdomain {
-                if self.caches.negative_cfg.two_hit {
-                    if self.caches.negative.get(&key).await.is_none() {
-                        if self.caches.negative_probe.get(&key).await.is_some() {
-                            let ttl = self.caches.clamp_negative_ttl(self.caches.negative_ttl);
-                            let entry = CachedEntry::new(bytes.clone(), ttl, self.caches.stale_window());
-                            self.caches.negative.insert(key.clone(), entry).await;
-                        } else {
-                            self.caches.negative_probe.insert(key.clone(), 1).await;
-                        }
-                    }
-                } else {
-                    let ttl = self.caches.clamp_negative_ttl(self.caches.negative_ttl);
-                    let entry = CachedEntry::new(bytes.clone(), ttl, self.caches.stale_window());
-                    self.caches.negative.insert(key.clone(), entry).await;
-                }
+        let mut m = Message::new();
+        m.set_id(req.id());
+        m.set_message_type(MessageType::Response);
+        m.set_op_code(OpCode::Query);
+        m.set_response_code(rcode);
+        m.set_recursion_desired(req.recursion_desired());
+        m.set_recursion_available(true);
+        m.set_authentic_data(secure);
+        m.add_answers(records.iter().cloned());
+        m.add_name_servers(authority.iter().cloned());
+
+        if bogus {
+            // RFC 8914: EDE 6 (DNSSEC Bogus) junto al SERVFAIL.
+            let mut edns = Edns::new();
+            edns.options_mut().insert(EdnsOption::Unknown(EDE_CODE, EDE_DNSSEC_BOGUS.to_be_bytes().to_vec()));
+            m.set_edns(edns);
+        }
+
+        // Con CD=1 la respuesta no se validó: no la guardamos para otros clientes.
+        if !checking_disabled {
+            if let Ok(bytes) = Self::encode_message(&m) {
+                self.store(&key, rcode, &records, &authority, secure, bytes).await;
             }
         }
 
-        response
-            .send_response(msg)
-            .await
-            .unwrap_or_else(|_| ResponseInfo::from(*req.header()))
+        self.send_message(req, &mut response, header, &m).await
     }
 }
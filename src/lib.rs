@@ -1,8 +1,29 @@
+pub mod atomic_file;
+pub mod authority;
 pub mod cache;
 pub mod config;
+pub mod consistency;
+pub mod ecs;
+pub mod exchange;
 pub mod filters;
 pub mod forwarder;
 pub mod handler;
+#[cfg(feature = "dnssec")]
+pub mod nsec_cache;
+pub mod nta;
+pub mod outbound;
+pub mod padding;
 pub mod recursor_engine;
+pub mod retry;
+#[cfg(feature = "dnssec")]
+pub mod rfc5011;
+#[cfg(feature = "dnssec")]
+pub mod root_mirror;
+pub mod roots;
+pub mod rrset_cache;
+#[cfg(feature = "dnssec")]
+pub mod trust_anchor;
+pub mod upstream;
+pub mod validation;
 pub mod zones;
 
@@ -1,10 +1,32 @@
 mod config;
+mod authority;
 mod cache;
+mod consistency;
+mod ecs;
 mod filters;
+mod exchange;
 mod zones;
+#[cfg(feature = "dnssec")]
+mod nsec_cache;
+mod nta;
+mod outbound;
 mod recursor_engine;
+mod retry;
+#[cfg(feature = "dnssec")]
+mod rfc5011;
+#[cfg(feature = "dnssec")]
+mod atomic_file;
+#[cfg(feature = "dnssec")]
+mod root_mirror;
+mod roots;
+mod rrset_cache;
+#[cfg(feature = "dnssec")]
+mod trust_anchor;
 mod forwarder;
+mod upstream;
+mod validation;
 mod handler;
+mod padding;
 
 use anyhow::Context;
 use tracing_subscriber::EnvFilter;
@@ -23,7 +45,7 @@ async fn main() -> anyhow::Result<()> {
         .with_context(|| format!("no pude cargar zones desde {}", cfg.zones.zones_dir))?;
 
     let filters = filters::Filters::from_config(&cfg.filters)?;
-    let caches = cache::DnsCaches::new(&cfg.cache);
+    let caches = cache::DnsCaches::new(&cfg.cache, cfg.recursor.max_cname_chain);
 
     // --- Decidir modo ---
     // Nota: en TOML, `upstreams = []` => Some(vec![]). Eso NO debería forzar forwarder.
@@ -36,11 +58,17 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("Modo: FORWARDER (upstreams={:?})", upstreams);
 
         // build_forwarder es async: hay que await antes de usar Context.
-        let resolver = forwarder::build_forwarder(&upstreams)
+        let fwd = forwarder::build_forwarder(&upstreams, &cfg.forwarder, &cfg.resolution)
             .await
-            .context("no pude crear forwarder")?;
+            .context("no pude crear forwarder")?
+            .with_query_padding(&padding::Padding::from_config(&cfg.padding));
 
-        handler::DnsHandler::new(cfg, zones, filters, caches, Some(resolver), None)
+        fwd.spawn_health_checks();
+        if cfg.forwarder.stats_interval_secs > 0 {
+            fwd.spawn_stats_logger(std::time::Duration::from_secs(cfg.forwarder.stats_interval_secs));
+        }
+
+        handler::DnsHandler::new(cfg, zones, filters, caches, Some(fwd), None)
     } else if is_recursor {
         tracing::info!("Modo: RECURSOR ITERATIVO (roots={})", cfg.roots.len());
 
@@ -48,6 +76,12 @@ async fn main() -> anyhow::Result<()> {
             .await
             .context("no pude crear recursor")?;
 
+        #[cfg(feature = "dnssec")]
+        recursor.spawn_anchor_rollover();
+        #[cfg(feature = "dnssec")]
+        recursor.spawn_root_mirror_refresh();
+        recursor.spawn_root_priming();
+
         handler::DnsHandler::new(cfg, zones, filters, caches, None, Some(recursor))
     } else {
         anyhow::bail!("roots está vacío y no hay upstreams: no puedo hacer recursión");
@@ -0,0 +1,362 @@
+//! Uso agresivo del cache validado (RFC 8198): con los rangos NSEC/NSEC3 de
+//! respuestas negativas ya validadas se sintetizan NXDOMAIN/NODATA sin
+//! consultar a los autoritativos (p.ej. ataques de subdominios aleatorios).
+//!
+//! Sólo se guardan pruebas de negativas `Secure`; cada rango vive
+//! min(TTL del NSEC, TTL de la SOA, MINIMUM de la SOA) (RFC 8198 §5.4).
+//! No se sintetiza a partir de comodines ni de NSEC3 con opt-out.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hickory_proto::dnssec::rdata::{DNSSECRData, NSEC, NSEC3};
+use hickory_proto::dnssec::Nsec3HashAlgorithm;
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+
+use crate::validation::DNAME;
+
+/// RFC 9276 §3.2: más iteraciones que esto no se cachean.
+const MAX_NSEC3_ITERATIONS: u16 = 150;
+
+/// Negativa armada desde el cache: SOA de la zona y las pruebas (NSEC/NSEC3 + RRSIG).
+#[derive(Debug, Clone)]
+pub struct Synthesized {
+    pub nxdomain: bool,
+    pub soa: Record,
+    pub proof: Vec<Record>,
+}
+
+pub struct NsecCache {
+    zones: Mutex<HashMap<Name, Zone>>,
+    max_entries: usize,
+}
+
+struct Zone {
+    soa: Record,
+    soa_expires: Instant,
+    nsec: BTreeMap<Name, Range>,
+    /// Clave: hash del dueño en base32hex (primer label del NSEC3).
+    nsec3: BTreeMap<String, Range>,
+    nsec3_params: Option<(Nsec3HashAlgorithm, Vec<u8>, u16)>,
+}
+
+#[derive(Clone)]
+struct Range {
+    /// NSEC/NSEC3 + sus RRSIG.
+    records: Vec<Record>,
+    expires: Instant,
+}
+
+impl Range {
+    fn nsec(&self) -> Option<&NSEC> {
+        self.records.iter().find_map(|r| match r.data() {
+            RData::DNSSEC(DNSSECRData::NSEC(n)) => Some(n),
+            _ => None,
+        })
+    }
+
+    fn nsec3(&self) -> Option<&NSEC3> {
+        self.records.iter().find_map(|r| match r.data() {
+            RData::DNSSEC(DNSSECRData::NSEC3(n)) => Some(n),
+            _ => None,
+        })
+    }
+}
+
+impl NsecCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            zones: Mutex::new(HashMap::new()),
+            max_entries,
+        }
+    }
+
+    pub fn clear(&self) {
+        self.zones.lock().unwrap().clear();
+    }
+
+    /// Guarda las pruebas de una negativa ya validada. `soa` es la SOA de la
+    /// zona que vino en authority; se ignoran registros de fuera de la zona.
+    pub fn insert(&self, soa: &Record, proof: &[Record]) {
+        let RData::SOA(soa_data) = soa.data() else {
+            return;
+        };
+        let zone_name = soa.name().to_lowercase();
+        let now = Instant::now();
+        let soa_ttl = soa.ttl().min(soa_data.minimum());
+
+        let mut zones = self.zones.lock().unwrap();
+        if entries(&zones) >= self.max_entries {
+            purge(&mut zones, now);
+            if entries(&zones) >= self.max_entries {
+                return;
+            }
+        }
+
+        let zone = zones.entry(zone_name.clone()).or_insert_with(|| Zone {
+            soa: soa.clone(),
+            soa_expires: now,
+            nsec: BTreeMap::new(),
+            nsec3: BTreeMap::new(),
+            nsec3_params: None,
+        });
+        zone.soa = soa.clone();
+        zone.soa_expires = now + Duration::from_secs(soa_ttl as u64);
+
+        for r in proof {
+            if !zone_name.zone_of(r.name()) {
+                continue;
+            }
+            let ttl = r.ttl().min(soa_ttl);
+            let sigs = proof.iter().filter(|s| {
+                s.name() == r.name()
+                    && matches!(s.data(), RData::DNSSEC(DNSSECRData::RRSIG(sig)) if sig.type_covered() == r.record_type())
+            });
+            let range = Range {
+                records: std::iter::once(r).chain(sigs).cloned().collect(),
+                expires: now + Duration::from_secs(ttl as u64),
+            };
+            match r.data() {
+                RData::DNSSEC(DNSSECRData::NSEC(_)) => {
+                    zone.nsec.insert(r.name().to_lowercase(), range);
+                }
+                RData::DNSSEC(DNSSECRData::NSEC3(n3)) => {
+                    if n3.iterations() > MAX_NSEC3_ITERATIONS {
+                        continue;
+                    }
+                    let params = (n3.hash_algorithm(), n3.salt().to_vec(), n3.iterations());
+                    if zone.nsec3_params.as_ref() != Some(&params) {
+                        // Cambió la cadena NSEC3 (resalt): se descarta la vieja.
+                        zone.nsec3.clear();
+                        zone.nsec3_params = Some(params);
+                    }
+                    let Some(label) = r.name().iter().next() else {
+                        continue;
+                    };
+                    let hash = String::from_utf8_lossy(label).to_ascii_lowercase();
+                    zone.nsec3.insert(hash, range);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// NXDOMAIN/NODATA para `qname`/`qtype` si las pruebas en cache alcanzan.
+    pub fn synthesize(&self, qname: &Name, qtype: RecordType) -> Option<Synthesized> {
+        let qname = qname.to_lowercase();
+        let now = Instant::now();
+        let zones = self.zones.lock().unwrap();
+
+        // Zona más profunda que contiene el nombre.
+        let (zone_name, zone) = zones
+            .iter()
+            .filter(|(z, zone)| z.zone_of(&qname) && zone.soa_expires > now)
+            .max_by_key(|(z, _)| z.num_labels())?;
+
+        // El DS vive en la zona padre: la cadena del hijo no sirve.
+        if qtype == RecordType::DS && *zone_name == qname {
+            return None;
+        }
+
+        let (nxdomain, ranges) = if zone.nsec3_params.is_some() && zone.nsec.is_empty() {
+            zone.nsec3_proof(zone_name, &qname, qtype, now)?
+        } else {
+            zone.nsec_proof(&qname, qtype, now)?
+        };
+
+        let remaining = ranges
+            .iter()
+            .map(|r| r.expires)
+            .chain([zone.soa_expires])
+            .min()
+            .map(|t| t.saturating_duration_since(now).as_secs() as u32)
+            .filter(|ttl| *ttl > 0)?;
+
+        let mut soa = zone.soa.clone();
+        soa.set_ttl(remaining);
+        let mut proof: Vec<Record> = Vec::new();
+        for range in ranges {
+            for r in &range.records {
+                if !proof.contains(r) {
+                    let mut r = r.clone();
+                    r.set_ttl(remaining);
+                    proof.push(r);
+                }
+            }
+        }
+        Some(Synthesized { nxdomain, soa, proof })
+    }
+}
+
+impl Zone {
+    /// NSEC (RFC 4035 §5.4): NODATA por coincidencia exacta, NXDOMAIN por
+    /// rango que cubre el nombre + rango que cubre el comodín del encloser.
+    fn nsec_proof(&self, qname: &Name, qtype: RecordType, now: Instant) -> Option<(bool, Vec<&Range>)> {
+        if let Some(range) = self.nsec.get(qname).filter(|r| r.expires > now) {
+            let nsec = range.nsec()?;
+            let types: Vec<RecordType> = nsec.type_bit_maps().collect();
+            if types.contains(&qtype) || types.contains(&RecordType::CNAME) {
+                return None;
+            }
+            // En un corte de zona sólo el DS es de esta zona.
+            if is_delegation(&types) && qtype != RecordType::DS {
+                return None;
+            }
+            return Some((false, vec![range]));
+        }
+
+        let (owner, range) = self.nsec_covering(qname, now)?;
+        let nsec = range.nsec()?;
+        let next = nsec.next_domain_name().to_lowercase();
+        let types: Vec<RecordType> = nsec.type_bit_maps().collect();
+        if owner.zone_of(qname) && (is_delegation(&types) || types.contains(&DNAME)) {
+            return None;
+        }
+
+        // El siguiente nombre está debajo de qname: qname es un empty non-terminal.
+        if qname.zone_of(&next) && next != *qname {
+            return Some((false, vec![range]));
+        }
+
+        let encloser = [owner, &next]
+            .into_iter()
+            .map(|n| common_ancestor(qname, n))
+            .max_by_key(|n| n.num_labels())?;
+        let wildcard = encloser.prepend_label("*").ok()?;
+        let (_, wild_range) = self.nsec_covering(&wildcard, now)?;
+        Some((true, vec![range, wild_range]))
+    }
+
+    /// NSEC con dueño < name < siguiente.
+    fn nsec_covering(&self, name: &Name, now: Instant) -> Option<(&Name, &Range)> {
+        let (owner, range) = self.nsec.range(..name.clone()).next_back().or_else(|| self.nsec.last_key_value())?;
+        if range.expires <= now {
+            return None;
+        }
+        let next = range.nsec()?.next_domain_name().to_lowercase();
+        covers(owner, &next, name).then_some((owner, range))
+    }
+
+    /// NSEC3 (RFC 5155 §8.4-8.6): NODATA por hash exacto, NXDOMAIN por prueba
+    /// de closest encloser (encloser exacto, next closer y comodín cubiertos).
+    fn nsec3_proof(
+        &self,
+        zone: &Name,
+        qname: &Name,
+        qtype: RecordType,
+        now: Instant,
+    ) -> Option<(bool, Vec<&Range>)> {
+        let hash = |n: &Name| self.nsec3_hash(n);
+
+        if let Some(range) = self.nsec3_matching(&hash(qname)?, now) {
+            let types: Vec<RecordType> = range.nsec3()?.type_bit_maps().collect();
+            if types.contains(&qtype) || types.contains(&RecordType::CNAME) || is_delegation(&types) {
+                return None;
+            }
+            return Some((false, vec![range]));
+        }
+
+        // Closest encloser: el ancestro más cercano con NSEC3 propio.
+        let mut next_closer = qname.clone();
+        let mut encloser = qname.base_name();
+        let ce_range = loop {
+            if let Some(range) = self.nsec3_matching(&hash(&encloser)?, now) {
+                break range;
+            }
+            if encloser == *zone || !zone.zone_of(&encloser) {
+                return None;
+            }
+            next_closer = encloser.clone();
+            encloser = encloser.base_name();
+        };
+        let ce_types: Vec<RecordType> = ce_range.nsec3()?.type_bit_maps().collect();
+        if is_delegation(&ce_types) || ce_types.contains(&DNAME) {
+            return None;
+        }
+
+        let nc_range = self.nsec3_covering(&hash(&next_closer)?, now)?;
+        let wildcard = encloser.prepend_label("*").ok()?;
+        let wild_range = self.nsec3_covering(&hash(&wildcard)?, now)?;
+        Some((true, vec![ce_range, nc_range, wild_range]))
+    }
+
+    fn nsec3_hash(&self, name: &Name) -> Option<String> {
+        let (alg, salt, iterations) = self.nsec3_params.as_ref()?;
+        let digest = alg.hash(salt, name, *iterations).ok()?;
+        Some(base32hex(digest.as_ref()))
+    }
+
+    fn nsec3_matching(&self, hash: &str, now: Instant) -> Option<&Range> {
+        self.nsec3.get(hash).filter(|r| r.expires > now)
+    }
+
+    /// NSEC3 que cubre el hash sin coincidir; con opt-out no prueba inexistencia.
+    fn nsec3_covering(&self, hash: &str, now: Instant) -> Option<&Range> {
+        let (owner, range) = self
+            .nsec3
+            .range(..hash.to_string())
+            .next_back()
+            .or_else(|| self.nsec3.last_key_value())?;
+        let nsec3 = range.nsec3()?;
+        if range.expires <= now || nsec3.opt_out() {
+            return None;
+        }
+        let next = base32hex(nsec3.next_hashed_owner_name());
+        covers(owner.as_str(), next.as_str(), hash).then_some(range)
+    }
+}
+
+/// ¿El rango (owner, next) cubre `name`? El último de la cadena da la vuelta.
+fn covers<T: Ord + ?Sized>(owner: &T, next: &T, name: &T) -> bool {
+    if owner < next {
+        owner < name && name < next
+    } else {
+        owner < name || name < next
+    }
+}
+
+fn is_delegation(types: &[RecordType]) -> bool {
+    types.contains(&RecordType::NS) && !types.contains(&RecordType::SOA)
+}
+
+/// Ancestro común más largo de `a` y `b`.
+fn common_ancestor(a: &Name, b: &Name) -> Name {
+    let mut n = a.clone();
+    while !n.zone_of(b) {
+        n = n.base_name();
+    }
+    n
+}
+
+fn entries(zones: &HashMap<Name, Zone>) -> usize {
+    zones.values().map(|z| z.nsec.len() + z.nsec3.len()).sum()
+}
+
+fn purge(zones: &mut HashMap<Name, Zone>, now: Instant) {
+    for zone in zones.values_mut() {
+        zone.nsec.retain(|_, r| r.expires > now);
+        zone.nsec3.retain(|_, r| r.expires > now);
+    }
+    zones.retain(|_, z| z.soa_expires > now || !z.nsec.is_empty() || !z.nsec3.is_empty());
+}
+
+/// Base32 "extended hex" (RFC 4648 §7) en minúsculas y sin padding, como el
+/// primer label de los dueños NSEC3.
+pub fn base32hex(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for &b in bytes {
+        acc = (acc << 8) | b as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((acc >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((acc << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
@@ -0,0 +1,84 @@
+//! Negative trust anchors (RFC 7646): dominios debajo de los cuales no se
+//! valida DNSSEC, para que un rollover roto del operador no deje el dominio
+//! inaccesible. Cada NTA vence solo; se cargan desde `[[recursor.nta]]` y se
+//! pueden agregar/quitar en caliente (ver `handler`).
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hickory_proto::rr::Name;
+
+use crate::config::NtaConfig;
+
+struct Entry {
+    domain: Name,
+    expires: Instant,
+}
+
+#[derive(Default)]
+pub struct NegativeTrustAnchors {
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl NegativeTrustAnchors {
+    pub fn from_config(cfg: &[NtaConfig]) -> anyhow::Result<Self> {
+        let nta = Self::default();
+        for c in cfg {
+            let domain = parse_domain(&c.domain)
+                .map_err(|e| anyhow::anyhow!("recursor.nta inválido: {}: {e}", c.domain))?;
+            nta.insert(domain, Duration::from_secs(c.lifetime_secs));
+        }
+        Ok(nta)
+    }
+
+    /// Agrega (o renueva) un NTA que vence en `lifetime`.
+    pub fn insert(&self, domain: Name, lifetime: Duration) {
+        let domain = domain.to_lowercase();
+        tracing::info!("NTA {domain}: sin validación DNSSEC por {}s", lifetime.as_secs());
+        let expires = Instant::now() + lifetime;
+        let mut entries = self.entries.lock().unwrap();
+        match entries.iter_mut().find(|e| e.domain == domain) {
+            Some(e) => e.expires = expires,
+            None => entries.push(Entry { domain, expires }),
+        }
+    }
+
+    /// Quita un NTA; `false` si no estaba.
+    pub fn remove(&self, domain: &Name) -> bool {
+        let domain = domain.to_lowercase();
+        let mut entries = self.entries.lock().unwrap();
+        let len = entries.len();
+        entries.retain(|e| e.domain != domain);
+        len != entries.len()
+    }
+
+    /// ¿`name` está debajo de un NTA vigente? Los vencidos se descartan.
+    pub fn covers(&self, name: &Name) -> bool {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|e| {
+            let alive = e.expires > now;
+            if !alive {
+                tracing::info!("NTA {}: vencido, se vuelve a validar", e.domain);
+            }
+            alive
+        });
+        entries.iter().any(|e| e.domain.zone_of(name))
+    }
+
+    /// `nta=<dominio> vence_en=<s>` por cada NTA vigente.
+    pub fn status_lines(&self) -> Vec<String> {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.expires > now)
+            .map(|e| format!("nta={} vence_en={}s", e.domain, (e.expires - now).as_secs()))
+            .collect()
+    }
+}
+
+pub fn parse_domain(s: &str) -> Result<Name, hickory_proto::ProtoError> {
+    Name::from_ascii(s.trim_end_matches('.')).map(|n| n.to_lowercase())
+}
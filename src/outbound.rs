@@ -0,0 +1,98 @@
+//! Consultas salientes del recursor: familias (IPv4/IPv6) habilitadas y el
+//! largo de las cadenas CNAME.
+//!
+//! Dirección y puertos de origen quedan en manos del sistema y de hickory
+//! (puerto UDP al azar por consulta): hickory 0.25 arma los servidores de
+//! cada delegación con su propio proveedor de conexiones y no deja fijarlos.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use hickory_proto::op::Message;
+use hickory_proto::rr::{Name, RData, Record};
+use ipnet::IpNet;
+
+use crate::config::RecursorConfig;
+use crate::exchange;
+
+#[derive(Debug, Clone)]
+pub struct Outbound {
+    pub families: Families,
+    pub max_cname_chain: usize,
+}
+
+impl Outbound {
+    pub fn from_config(cfg: &RecursorConfig) -> anyhow::Result<Self> {
+        let families = Families::new(cfg.ipv4, cfg.ipv6)?;
+        Ok(Self {
+            families,
+            max_cname_chain: cfg.max_cname_chain,
+        })
+    }
+
+    /// Consulta directa a un servidor (priming, copia de la raíz).
+    pub async fn query(&self, addr: SocketAddr, msg: Message, timeout: Duration) -> anyhow::Result<Message> {
+        exchange::query(addr, msg, timeout).await
+    }
+}
+
+/// Familias de direcciones habilitadas para hablar con roots y autoritativos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Families {
+    pub ipv4: bool,
+    pub ipv6: bool,
+}
+
+impl Families {
+    pub fn new(ipv4: bool, ipv6: bool) -> anyhow::Result<Self> {
+        if !ipv4 && !ipv6 {
+            anyhow::bail!("recursor: ipv4 e ipv6 no pueden estar los dos en false");
+        }
+        Ok(Self { ipv4, ipv6 })
+    }
+
+    pub fn allows(&self, addr: &SocketAddr) -> bool {
+        if addr.is_ipv4() {
+            self.ipv4
+        } else {
+            self.ipv6
+        }
+    }
+
+    /// Saca las direcciones de familias deshabilitadas (hints y priming).
+    pub fn select(&self, addrs: &mut Vec<SocketAddr>) {
+        addrs.retain(|a| self.allows(a));
+    }
+
+    /// Redes a agregar al filtro de destinos de hickory, que lo aplica a los
+    /// pools de NS que arma en cada delegación: así no habla con autoritativos
+    /// de una familia deshabilitada.
+    pub fn deny_nets(&self) -> Vec<IpNet> {
+        let mut out = Vec::new();
+        if !self.ipv4 {
+            out.push("0.0.0.0/0".parse().unwrap());
+        }
+        if !self.ipv6 {
+            out.push("::/0".parse().unwrap());
+        }
+        out
+    }
+}
+
+/// Largo de la cadena CNAME que arranca en `qname` dentro de `records`
+/// (se corta si hay un ciclo).
+pub fn cname_chain(qname: &Name, records: &[Record]) -> usize {
+    let mut seen: Vec<&Name> = Vec::new();
+    let mut name = qname;
+    while let Some(target) = records.iter().find_map(|r| match r.data() {
+        RData::CNAME(c) if r.name() == name => Some(&c.0),
+        _ => None,
+    }) {
+        if seen.contains(&target) {
+            break;
+        }
+        seen.push(target);
+        name = target;
+    }
+    seen.len()
+}
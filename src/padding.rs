@@ -0,0 +1,90 @@
+use crate::config::{PaddingConfig, TransportPadding};
+use hickory_proto::op::{Edns, Message};
+use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+use hickory_proto::xfer::Protocol;
+
+/// Código de opción EDNS(0) Padding (RFC 7830).
+const PADDING_CODE: u16 = 12;
+
+/// Cabecera de una opción EDNS: OPTION-CODE (2) + OPTION-LENGTH (2).
+const OPTION_HEADER_LEN: usize = 4;
+
+/// Política de padding "block-length" (RFC 8467, sección 4.1).
+///
+/// Se resuelve por transporte: UDP plano nunca se rellena, sin importar
+/// lo que diga la config.
+#[derive(Debug, Clone)]
+pub struct Padding {
+    responses: TransportPadding,
+    queries: TransportPadding,
+}
+
+impl Padding {
+    pub fn from_config(cfg: &PaddingConfig) -> Self {
+        Self {
+            responses: cfg.responses.clone(),
+            queries: cfg.queries.clone(),
+        }
+    }
+
+    /// Tamaño de bloque para respuestas servidas por `protocol`.
+    pub fn response_block(&self, protocol: Protocol) -> Option<u16> {
+        block_for(&self.responses, protocol)
+    }
+
+    /// Tamaño de bloque para consultas enviadas a un upstream por `protocol`.
+    pub fn query_block(&self, protocol: Protocol) -> Option<u16> {
+        block_for(&self.queries, protocol)
+    }
+}
+
+fn block_for(t: &TransportPadding, protocol: Protocol) -> Option<u16> {
+    // `Protocol` cambia de variantes según features de hickory; usamos su nombre.
+    let block = match protocol.to_string().as_str() {
+        "tcp" => t.tcp,
+        "tls" => t.tls,
+        "https" | "h3" => t.https,
+        "quic" => t.quic,
+        _ => 0,
+    };
+    (block > 0).then_some(block)
+}
+
+/// ¿El mensaje trae la opción Padding? (RFC 7830: sólo se rellena la
+/// respuesta si el cliente la incluyó en la consulta).
+pub fn has_padding(edns: &Edns) -> bool {
+    edns.option(EdnsCode::Padding).is_some()
+}
+
+/// Agrega (o reemplaza) la opción Padding para que el mensaje codificado
+/// quede en un múltiplo de `block` bytes.
+///
+/// Si el mensaje no tiene EDNS se agrega uno por defecto.
+pub fn pad_message(msg: &mut Message, block: u16) -> anyhow::Result<()> {
+    let edns = msg.extensions_mut().get_or_insert_with(Edns::new);
+    edns.options_mut().remove(EdnsCode::Padding);
+
+    let len = msg.to_vec()?.len();
+    if let Some(edns) = msg.extensions_mut() {
+        pad_edns(edns, len, block);
+    }
+    Ok(())
+}
+
+/// Igual que `pad_message` pero sobre el EDNS suelto: `unpadded_len` es el
+/// largo del mensaje codificado con este EDNS y sin opción Padding.
+pub fn pad_edns(edns: &mut Edns, unpadded_len: usize, block: u16) {
+    edns.options_mut().remove(EdnsCode::Padding);
+
+    // La opción vacía ya suma su cabecera de 4 bytes.
+    let pad = padding_len(unpadded_len + OPTION_HEADER_LEN, block as usize);
+    edns.options_mut()
+        .insert(EdnsOption::Unknown(PADDING_CODE, vec![0u8; pad]));
+}
+
+fn padding_len(len: usize, block: usize) -> usize {
+    if block == 0 {
+        return 0;
+    }
+    (block - len % block) % block
+}
@@ -1,51 +1,199 @@
-use crate::config::AppConfig;
-use anyhow::Context;
-use hickory_recursor::{DnssecPolicy, Recursor, RecursorBuilder};
+use crate::authority::ZoneLimits;
+use crate::config::{AppConfig, RecursorConfig};
+use crate::nta::NegativeTrustAnchors;
+use crate::outbound::{self, Outbound};
+use crate::retry::RetryPolicy;
+use crate::roots;
+use hickory_recursor::{DnssecPolicy, ErrorKind, Recursor};
 use hickory_recursor::resolver::config::{NameServerConfig, NameServerConfigGroup};
+use hickory_recursor::resolver::dns_lru::TtlConfig;
+use hickory_recursor::resolver::lookup::Lookup;
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::{Name, Record, RecordType};
 use hickory_proto::xfer::Protocol;
 use ipnet::IpNet;
-use std::net::{IpAddr, SocketAddr};
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+#[cfg(feature = "dnssec")]
+use crate::nsec_cache::{NsecCache, Synthesized};
+#[cfg(feature = "dnssec")]
+use crate::rfc5011::AnchorState;
+#[cfg(feature = "dnssec")]
+use crate::root_mirror::RootMirror;
+#[cfg(feature = "dnssec")]
+use hickory_proto::dnssec::rdata::DNSKEY;
+#[cfg(feature = "dnssec")]
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub struct RecursorEngine {
-    recursor: Arc<Recursor>,
+    /// Recursor validante; se reemplaza entero si cambian los trust anchors (RFC 5011).
+    recursor: Arc<RwLock<Arc<Recursor>>>,
+    /// Con validación activa: recursor sin validar para las consultas CD=1
+    /// (caches propios, no se mezclan respuestas sin validar).
+    unchecked: Arc<RwLock<Option<Arc<Recursor>>>>,
+    factory: Arc<Factory>,
+    /// Política del recursor de servicio (sin los anchors de RFC 5011, que
+    /// salen de `rollover`).
+    policy: DnssecPolicy,
+    priming: Option<Arc<Priming>>,
+    /// Dominios en los que no se valida (RFC 7646); usan `unchecked`.
+    nta: Arc<NegativeTrustAnchors>,
+    #[cfg(feature = "dnssec")]
+    rollover: Option<Arc<Rollover>>,
+    /// Rangos NSEC/NSEC3 validados para sintetizar negativas (RFC 8198).
+    #[cfg(feature = "dnssec")]
+    nsec: Option<Arc<NsecCache>>,
+    /// Copia local de la raíz (RFC 8806), único root del recursor.
+    #[cfg(feature = "dnssec")]
+    root_mirror: Option<Arc<RootMirror>>,
+    /// Topes de resoluciones por zona (`[recursor.authority]`).
+    zones: Arc<ZoneLimits>,
     timeout: Duration,
-    attempts: usize,
+    retry: RetryPolicy,
 }
 
-impl RecursorEngine {
-    pub async fn new(cfg: &AppConfig) -> anyhow::Result<Self> {
-        if cfg.roots.is_empty() {
-            anyhow::bail!("roots está vacío y no hay upstreams: no puedo hacer recursión iterativa");
-        }
+/// Entradas del cache de registros de hickory. Es sólo de trabajo: lo que
+/// una resolución en curso necesita (direcciones de NS sin glue, DS/DNSKEY de
+/// la cadena DNSSEC). Las respuestas se sirven desde `DnsCaches`.
+const RECORD_CACHE_SIZE: usize = 1024;
+
+/// Lo necesario para armar un `Recursor` con una política DNSSEC dada.
+struct Factory {
+    cfg: RecursorConfig,
+    /// Clamps del cache de registros de hickory: los de `[cache]`.
+    ttl: TtlConfig,
+    /// Roots en uso; el priming los reemplaza.
+    roots: RwLock<NameServerConfigGroup>,
+    outbound: Outbound,
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl Factory {
+    fn build(&self, policy: DnssecPolicy) -> Result<Recursor, hickory_recursor::Error> {
+        let roots = self.roots.read().unwrap().clone();
+        Recursor::builder()
+            .ns_cache_size(self.cfg.ns_cache_size)
+            .record_cache_size(RECORD_CACHE_SIZE)
+            .ttl_config(self.ttl.clone())
+            .recursion_limit(Some(self.cfg.recursion_limit))
+            .ns_recursion_limit(Some(self.cfg.ns_recursion_limit))
+            .case_randomization(self.cfg.case_randomization)
+            .nameserver_filter(self.allow.iter(), self.deny.iter())
+            .dnssec_policy(policy)
+            .build(roots)
+    }
 
-        // Root hints
-        let mut roots: Vec<NameServerConfig> = Vec::new();
-        for ip_str in &cfg.roots {
-            let ip_addr: IpAddr = ip_str
-                .parse()
-                .with_context(|| format!("root ip inválida: {ip_str}"))?;
+    fn set_roots(&self, addrs: &[SocketAddr]) {
+        *self.roots.write().unwrap() = root_group(addrs);
+    }
+}
 
+/// UDP + TCP por root (el priming puede venir truncado).
+fn root_group(addrs: &[SocketAddr]) -> NameServerConfigGroup {
+    let mut roots: Vec<NameServerConfig> = Vec::new();
+    for &addr in addrs {
+        for protocol in [Protocol::Udp, Protocol::Tcp] {
             roots.push(NameServerConfig {
-                socket_addr: SocketAddr::new(ip_addr, 53),
-                protocol: Protocol::Udp,
+                socket_addr: addr,
+                protocol,
                 tls_dns_name: None,
                 trust_negative_responses: true,
                 bind_addr: None,
                 http_endpoint: None,
             });
         }
+    }
+    NameServerConfigGroup::from(roots)
+}
+
+/// Priming periódico de la raíz (RFC 8109).
+struct Priming {
+    hints: Vec<SocketAddr>,
+    /// Con roots de laboratorio (fuera del puerto 53) el set primado no se
+    /// aplica: sólo se avisa si difiere.
+    apply: bool,
+    interval: Duration,
+    /// Set de roots en uso y espera hasta el próximo priming.
+    state: Mutex<(Vec<SocketAddr>, Duration)>,
+}
+
+/// Espera tras un priming fallido.
+const PRIMING_RETRY: Duration = Duration::from_secs(300);
+/// Piso entre primings (TTL de `. NS` absurdamente bajos).
+const PRIMING_MIN: Duration = Duration::from_secs(60);
+
+impl Priming {
+    fn next_wait(&self, ttl: u32) -> Duration {
+        self.interval.min(Duration::from_secs(ttl.into())).max(PRIMING_MIN)
+    }
+
+    /// `. NS` contra los roots en uso y después los hints que falten.
+    async fn prime(&self, outbound: &Outbound, timeout: Duration) -> anyhow::Result<roots::Primed> {
+        let mut candidates = self.state.lock().unwrap().0.clone();
+        for hint in &self.hints {
+            if !candidates.contains(hint) {
+                candidates.push(*hint);
+            }
+        }
+        let primed = roots::prime(&candidates, outbound, timeout).await?;
+        if let Some(diff) = roots::divergence(&self.hints, &primed.addrs) {
+            tracing::warn!("priming: los hints de `roots` no coinciden con la raíz: {diff}");
+        }
+        Ok(primed)
+    }
+}
+
+impl RecursorEngine {
+    pub async fn new(cfg: &AppConfig) -> anyhow::Result<Self> {
+        if cfg.roots.is_empty() {
+            anyhow::bail!("roots está vacío y no hay upstreams: no puedo hacer recursión iterativa");
+        }
 
-        let root_group = NameServerConfigGroup::from(roots);
+        let outbound = Outbound::from_config(&cfg.recursor)?;
+        let mut hints = roots::load(&cfg.roots)?;
+        outbound.families.select(&mut hints);
+        if hints.is_empty() {
+            anyhow::bail!("roots no tiene ningún servidor utilizable (¿ipv4/ipv6 deshabilitado?)");
+        }
+        tracing::info!("roots: {} servidores", hints.len());
+
+        // Con copia local de la raíz no hace falta priming.
+        let timeout = Duration::from_millis(cfg.recursor.timeout_ms);
+        let (addrs, priming) = if cfg.recursor.priming && cfg.recursor.root_mirror.is_none() {
+            let (addrs, priming) = prime_at_start(&cfg.recursor, hints, &outbound, timeout).await;
+            (addrs, Some(Arc::new(priming)))
+        } else {
+            (hints, None)
+        };
 
-        let mut builder: RecursorBuilder = Recursor::builder()
-            .ns_cache_size(cfg.recursor.ns_cache_size)
-            .record_cache_size(cfg.recursor.record_cache_size)
-            .recursion_limit(Some(cfg.recursor.recursion_limit))
-            .ns_recursion_limit(Some(cfg.recursor.ns_recursion_limit))
-            .case_randomization(cfg.recursor.case_randomization);
+        // Copia local de la raíz: el recursor sólo habla con ella; los roots
+        // quedan para cuando la copia vence.
+        #[cfg(feature = "dnssec")]
+        let (addrs, root_mirror) = match &cfg.recursor.root_mirror {
+            Some(m) => {
+                let listen: std::net::SocketAddr = m
+                    .listen
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("recursor.root_mirror.listen inválido: {}: {e}", m.listen))?;
+                if !outbound.families.allows(&listen) {
+                    anyhow::bail!("recursor.root_mirror.listen {listen}: su familia está deshabilitada (ipv4/ipv6)");
+                }
+                if !listen.ip().is_loopback() {
+                    tracing::warn!("recursor.root_mirror.listen {listen} no es loopback (RFC 8806 §2)");
+                }
+                let mirror = Arc::new(RootMirror::new(m, addrs, outbound.clone(), timeout)?);
+                mirror.serve(listen).await?;
+                (vec![listen], Some(mirror))
+            }
+            None => (addrs, None),
+        };
+        #[cfg(not(feature = "dnssec"))]
+        if cfg.recursor.root_mirror.is_some() {
+            anyhow::bail!("recursor.root_mirror requiere compilar con --features dnssec");
+        }
 
         // nameserver filter (destinos)
         let allow: Vec<IpNet> = cfg
@@ -54,49 +202,727 @@ impl RecursorEngine {
             .iter()
             .filter_map(|s| s.parse().ok())
             .collect();
-        let deny: Vec<IpNet> = cfg
+        let mut deny: Vec<IpNet> = cfg
             .filters
             .deny_nets
             .iter()
             .filter_map(|s| s.parse().ok())
             .collect();
-        builder = builder.nameserver_filter(allow.iter(), deny.iter());
+        deny.extend(outbound.families.deny_nets());
+
+        if cfg.recursor.attempts.is_some() {
+            tracing::warn!("recursor.attempts está obsoleto: los reintentos los define [resolution]");
+        }
+        // El cache de hickory es aparte del front, pero con los mismos clamps.
+        let secs = Duration::from_secs;
+        let ttl = TtlConfig::new(
+            Some(secs(cfg.cache.min_ttl)),
+            Some(secs(cfg.cache.negative.min_ttl)),
+            Some(secs(cfg.cache.max_ttl)),
+            Some(secs(cfg.cache.negative.max_ttl)),
+        );
 
-        // DNSSEC policy
-        builder = builder.dnssec_policy(parse_dnssec_policy(&cfg.recursor.dnssec)?);
+        let factory = Arc::new(Factory {
+            cfg: cfg.recursor.clone(),
+            ttl,
+            roots: RwLock::new(root_group(&addrs)),
+            outbound,
+            allow,
+            deny,
+        });
 
-        let recursor = builder.build(root_group)?;
+        // DNSSEC policy (+ trust anchors desde archivo / RFC 5011)
+        let policy = parse_dnssec_policy(&cfg.recursor.dnssec)?;
+        #[cfg(feature = "dnssec")]
+        let (policy, rollover) = trust_anchors(policy, &factory).await?;
+        // El ZONEMD de la copia se valida con los mismos anchors; hasta acá
+        // el mirror reenviaba a los roots (p. ej. para resolver los DS).
+        #[cfg(feature = "dnssec")]
+        if let Some(mirror) = &root_mirror {
+            mirror.set_anchors(anchor_keys(&policy));
+            mirror.load()?;
+        }
+        #[cfg(not(feature = "dnssec"))]
+        if cfg.recursor.trust_anchor_file.is_some() || cfg.recursor.rfc5011.is_set() {
+            anyhow::bail!("recursor.trust_anchor_file y recursor.rfc5011 requieren compilar con --features dnssec");
+        }
+        #[cfg(not(feature = "dnssec"))]
+        if cfg.recursor.aggressive_nsec || cfg.recursor.aggressive_nsec_size.is_some() {
+            anyhow::bail!("recursor.aggressive_nsec y recursor.aggressive_nsec_size requieren compilar con --features dnssec");
+        }
+
+        let unchecked = if is_validating(&policy) {
+            Some(Arc::new(factory.build(unchecked_policy())?))
+        } else {
+            None
+        };
+        let recursor = factory.build(policy.clone())?;
+
+        let nta = NegativeTrustAnchors::from_config(&cfg.recursor.nta)?;
+        if !cfg.recursor.nta.is_empty() && unchecked.is_none() {
+            tracing::warn!("recursor.nta sin dnssec = \"validate\": no tiene efecto");
+        }
+        #[cfg(feature = "dnssec")]
+        let nsec = match (cfg.recursor.aggressive_nsec, unchecked.is_some()) {
+            (true, true) => Some(Arc::new(NsecCache::new(cfg.recursor.aggressive_nsec_size))),
+            (true, false) => {
+                tracing::warn!("recursor.aggressive_nsec sin dnssec = \"validate\": no tiene efecto");
+                None
+            }
+            (false, _) => None,
+        };
 
         Ok(Self {
-            recursor: Arc::new(recursor),
-            timeout: Duration::from_millis(cfg.recursor.timeout_ms),
-            attempts: cfg.recursor.attempts.max(1),
+            recursor: Arc::new(RwLock::new(Arc::new(recursor))),
+            unchecked: Arc::new(RwLock::new(unchecked)),
+            factory,
+            policy,
+            priming,
+            nta: Arc::new(nta),
+            #[cfg(feature = "dnssec")]
+            rollover: rollover.map(Arc::new),
+            #[cfg(feature = "dnssec")]
+            nsec,
+            #[cfg(feature = "dnssec")]
+            root_mirror,
+            zones: Arc::new(ZoneLimits::from_config(&cfg.recursor.authority)?),
+            timeout,
+            retry: RetryPolicy::from_config(&cfg.resolution),
         })
     }
 
+    pub fn ntas(&self) -> &NegativeTrustAnchors {
+        &self.nta
+    }
+
+    /// Líneas para `status.server.`.
+    pub fn status_lines(&self) -> Vec<String> {
+        let mut lines = self.nta.status_lines();
+        lines.extend(self.zones.status_lines());
+        #[cfg(feature = "dnssec")]
+        lines.extend(self.root_mirror.as_ref().map(|m| m.status_line()));
+        lines
+    }
+
     pub async fn resolve(
         &self,
-        qname: hickory_proto::rr::Name,
-        qtype: hickory_proto::rr::RecordType,
+        qname: Name,
+        qtype: RecordType,
         do_bit: bool,
-    ) -> anyhow::Result<hickory_recursor::resolver::lookup::Lookup> {
+        checking_disabled: bool,
+    ) -> Resolution {
         use hickory_proto::op::Query;
         use tokio::time::timeout;
 
-        let mut last_err: Option<anyhow::Error> = None;
+        let unchecked = match &*self.unchecked.read().unwrap() {
+            Some(unchecked) if checking_disabled || self.nta.covers(&qname) => Some(unchecked.clone()),
+            _ => None,
+        };
+
+        // Sólo con respuestas validadas: nunca para CD=1 ni debajo de un NTA.
+        #[cfg(feature = "dnssec")]
+        let nsec = self.nsec.as_ref().filter(|_| unchecked.is_none());
+        #[cfg(feature = "dnssec")]
+        if let Some(synth) = nsec.and_then(|c| c.synthesize(&qname, qtype)) {
+            tracing::debug!("{qname} {qtype}: negativa sintetizada desde NSEC en cache");
+            let mut res = Resolution::from(synth);
+            if !do_bit {
+                res.strip_dnssec();
+            }
+            return res;
+        }
+
+        // Zona con límites propios: al tope, SERVFAIL sin consultar (el permiso vive
+        // hasta el final de la resolución).
+        let _zone = match self.zones.zone(&qname) {
+            Ok(zone) => zone,
+            Err(refused) => {
+                tracing::debug!("{qname} {qtype}: no consulto ({refused})");
+                return Resolution::ServFail(format!("zona limitada: {refused}"));
+            }
+        };
 
-        for _ in 0..self.attempts {
+        let recursor = unchecked.unwrap_or_else(|| self.recursor.read().unwrap().clone());
+        let attempt = || {
             let q = Query::query(qname.clone(), qtype);
-            let fut = self.recursor.resolve(q, Instant::now(), do_bit);
+            let fut = recursor.resolve(q, Instant::now(), do_bit);
+            async move {
+                match timeout(self.timeout, fut).await {
+                    Ok(Ok(lookup)) => Resolution::from_lookup(lookup),
+                    Ok(Err(e)) => Resolution::from_error(e),
+                    Err(_) => Resolution::Timeout,
+                }
+            }
+        };
+
+        let mut res = self
+            .retry
+            .run(attempt, Resolution::is_retryable, || Resolution::Timeout)
+            .await;
+        let chain = outbound::cname_chain(&qname, res.answers());
+        if chain > self.factory.outbound.max_cname_chain {
+            tracing::debug!("{qname} {qtype}: cadena CNAME de {chain}");
+            res = Resolution::ServFail(format!("cadena CNAME de {chain} (máximo {})", self.factory.outbound.max_cname_chain));
+        }
+        #[cfg(feature = "dnssec")]
+        if let Some(cache) = nsec.filter(|_| res.security() == Security::Secure) {
+            if let Resolution::NxDomain { soa: Some(soa), proof } | Resolution::NoData { soa: Some(soa), proof } = &res {
+                cache.insert(soa, proof);
+            }
+        }
+        if !do_bit {
+            res.strip_dnssec();
+        }
+        res
+    }
+
+    /// Lanza el priming periódico de la raíz (si está activo).
+    pub fn spawn_root_priming(&self) {
+        let Some(priming) = self.priming.clone() else {
+            return;
+        };
+        let engine = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let wait = priming.state.lock().unwrap().1;
+                tokio::time::sleep(wait).await;
+                let wait = match engine.reprime(&priming).await {
+                    Ok(ttl) => priming.next_wait(ttl),
+                    Err(e) => {
+                        tracing::warn!("priming: {e:#}; sigo con los roots actuales");
+                        PRIMING_RETRY
+                    }
+                };
+                priming.state.lock().unwrap().1 = wait;
+            }
+        });
+    }
+
+    /// Repite el priming y, si cambió el set de roots, reconstruye los
+    /// recursores (caches nuevos). Devuelve el TTL de `. NS`.
+    async fn reprime(&self, priming: &Priming) -> anyhow::Result<u32> {
+        let primed = priming.prime(&self.factory.outbound, self.timeout).await?;
+        let current = priming.state.lock().unwrap().0.clone();
+        let changed = primed.addrs.len() != current.len() || primed.addrs.iter().any(|a| !current.contains(a));
+        if priming.apply && changed {
+            tracing::info!("priming: set de roots actualizado ({} servidores)", primed.addrs.len());
+            self.factory.set_roots(&primed.addrs);
+            self.rebuild()?;
+            priming.state.lock().unwrap().0 = primed.addrs;
+        }
+        Ok(primed.ttl)
+    }
+
+    /// Vacía los caches del recursor: hickory no expone cómo vaciar el suyo,
+    /// así que se reemplazan los recursores (y se olvidan los rangos NSEC).
+    pub fn flush_cache(&self) -> anyhow::Result<()> {
+        #[cfg(feature = "dnssec")]
+        if let Some(nsec) = &self.nsec {
+            nsec.clear();
+        }
+        self.rebuild()
+    }
+
+    /// Recursores nuevos con los roots actuales de la factory.
+    fn rebuild(&self) -> anyhow::Result<()> {
+        let unchecked = if self.unchecked.read().unwrap().is_some() {
+            Some(Arc::new(self.factory.build(unchecked_policy())?))
+        } else {
+            None
+        };
+        let recursor = self.factory.build(self.serving_policy())?;
+        *self.recursor.write().unwrap() = Arc::new(recursor);
+        *self.unchecked.write().unwrap() = unchecked;
+        Ok(())
+    }
+
+    /// Política del recursor de servicio, con los anchors vigentes.
+    fn serving_policy(&self) -> DnssecPolicy {
+        #[cfg(feature = "dnssec")]
+        if let Some(rollover) = &self.rollover {
+            return validate_with(rollover.anchors());
+        }
+        self.policy.clone()
+    }
+}
+
+/// Resultado de una resolución iterativa.
+#[derive(Debug, Clone)]
+pub enum Resolution {
+    Answer(Lookup),
+    /// El nombre no existe; SOA de la zona si vino (TTL negativo, RFC 2308)
+    /// y las pruebas DNSSEC (NSEC/NSEC3/RRSIG) de la authority.
+    NxDomain { soa: Option<Record>, proof: Vec<Record> },
+    /// El nombre existe pero no tiene registros de ese tipo.
+    NoData { soa: Option<Record>, proof: Vec<Record> },
+    /// La validación DNSSEC falló (RFC 4035 §5.5: SERVFAIL).
+    Bogus(String),
+    ServFail(String),
+    Timeout,
+}
+
+/// Estado DNSSEC de una respuesta, según las pruebas que dejó hickory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    #[cfg_attr(not(feature = "dnssec"), allow(dead_code))]
+    Secure,
+    Insecure,
+    Bogus,
+}
+
+impl Resolution {
+    pub fn from_lookup(lookup: Lookup) -> Self {
+        if lookup.records().is_empty() {
+            return Self::NoData {
+                soa: None,
+                proof: Vec::new(),
+            };
+        }
+        if security_of(lookup.records()) == Security::Bogus {
+            return Self::Bogus("respuesta con firmas DNSSEC inválidas".to_string());
+        }
+        Self::Answer(lookup)
+    }
+
+    pub fn from_error(e: hickory_recursor::Error) -> Self {
+        if e.is_timeout() || matches!(e.kind(), ErrorKind::Timeout) {
+            return Self::Timeout;
+        }
+        if e.is_nx_domain() || e.is_no_records_found() {
+            let nx = e.is_nx_domain();
+            let proof = proof_of(&e);
+            let soa = soa_of(e);
+            let res = if nx {
+                Self::NxDomain { soa, proof }
+            } else {
+                Self::NoData { soa, proof }
+            };
+            if security_of(&res.authority()) == Security::Bogus {
+                return Self::Bogus("negativa con pruebas DNSSEC inválidas".to_string());
+            }
+            return res;
+        }
+        #[cfg(feature = "dnssec")]
+        if nsec_proof_of(&e).is_some_and(|p| p.is_bogus()) {
+            return Self::Bogus("negativa sin prueba NSEC/NSEC3 válida".to_string());
+        }
+        match e.kind() {
+            ErrorKind::ForwardNS(_) => Self::ServFail("referral sin respuesta".to_string()),
+            _ => Self::ServFail(e.to_string()),
+        }
+    }
+
+    pub fn rcode(&self) -> ResponseCode {
+        match self {
+            Self::Answer(_) | Self::NoData { .. } => ResponseCode::NoError,
+            Self::NxDomain { .. } => ResponseCode::NXDomain,
+            Self::Bogus(_) | Self::ServFail(_) | Self::Timeout => ResponseCode::ServFail,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::ServFail(_) | Self::Timeout)
+    }
+
+    pub fn answers(&self) -> &[Record] {
+        match self {
+            Self::Answer(lookup) => lookup.records(),
+            _ => &[],
+        }
+    }
+
+    /// Sección authority de la respuesta: la SOA de los negativos y sus
+    /// pruebas DNSSEC.
+    pub fn authority(&self) -> Vec<Record> {
+        match self {
+            Self::NxDomain { soa, proof } | Self::NoData { soa, proof } => {
+                soa.iter().chain(proof).cloned().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Secure sólo si todo lo que se responde quedó validado.
+    pub fn security(&self) -> Security {
+        match self {
+            Self::Answer(lookup) => security_of(lookup.records()),
+            Self::NxDomain { .. } | Self::NoData { .. } => security_of(&self.authority()),
+            Self::Bogus(_) => Security::Bogus,
+            Self::ServFail(_) | Self::Timeout => Security::Insecure,
+        }
+    }
+
+    /// Saca RRSIG/NSEC/NSEC3 de la authority (cliente sin DO). Las answers ya
+    /// vienen filtradas por hickory según el DO de la consulta.
+    pub fn strip_dnssec(&mut self) {
+        if let Self::NxDomain { proof, .. } | Self::NoData { proof, .. } = self {
+            proof.clear();
+        }
+    }
+}
+
+#[cfg(feature = "dnssec")]
+impl From<Synthesized> for Resolution {
+    fn from(s: Synthesized) -> Self {
+        let (soa, proof) = (Some(s.soa), s.proof);
+        if s.nxdomain {
+            Self::NxDomain { soa, proof }
+        } else {
+            Self::NoData { soa, proof }
+        }
+    }
+}
+
+fn soa_of(e: hickory_recursor::Error) -> Option<Record> {
+    e.into_soa().map(|soa| soa.into_record_of_rdata())
+}
+
+/// NSEC/NSEC3/RRSIG de la authority de una negativa.
+fn proof_of(e: &hickory_recursor::Error) -> Vec<Record> {
+    let authorities = match e.kind() {
+        ErrorKind::Forward(fwd) => fwd.authorities.clone(),
+        ErrorKind::Proto(p) => authorities_of(p),
+        ErrorKind::Resolve(r) => r.proto().and_then(authorities_of),
+        _ => None,
+    };
+    authorities
+        .iter()
+        .flat_map(|a| a.iter())
+        .filter(|r| is_dnssec_type(r.record_type()))
+        .cloned()
+        .collect()
+}
+
+/// Prueba de una negativa que hickory no pudo validar (`ProtoErrorKind::Nsec`).
+#[cfg(feature = "dnssec")]
+fn nsec_proof_of(e: &hickory_recursor::Error) -> Option<hickory_proto::dnssec::Proof> {
+    let proto = match e.kind() {
+        ErrorKind::Proto(p) => Some(p),
+        ErrorKind::Resolve(r) => r.proto(),
+        _ => None,
+    }?;
+    match proto.kind() {
+        hickory_proto::ProtoErrorKind::Nsec { proof, .. } => Some(*proof),
+        _ => None,
+    }
+}
+
+fn authorities_of(p: &hickory_proto::ProtoError) -> Option<Arc<[Record]>> {
+    match p.kind() {
+        hickory_proto::ProtoErrorKind::NoRecordsFound { authorities, .. } => authorities.clone(),
+        _ => None,
+    }
+}
+
+pub fn is_dnssec_type(t: RecordType) -> bool {
+    matches!(t, RecordType::RRSIG | RecordType::NSEC | RecordType::NSEC3)
+}
+
+/// Las RRSIG no llevan prueba propia: se mira el resto.
+#[cfg(feature = "dnssec")]
+fn security_of(records: &[Record]) -> Security {
+    let mut checked = records.iter().filter(|r| r.record_type() != RecordType::RRSIG).peekable();
+    if checked.peek().is_none() {
+        return Security::Insecure;
+    }
+    let mut secure = true;
+    for r in checked {
+        let proof = r.proof();
+        if proof.is_bogus() {
+            return Security::Bogus;
+        }
+        secure &= proof.is_secure();
+    }
+    if secure {
+        Security::Secure
+    } else {
+        Security::Insecure
+    }
+}
+
+#[cfg(not(feature = "dnssec"))]
+fn security_of(_records: &[Record]) -> Security {
+    Security::Insecure
+}
+
+/// Priming al arrancar. Si falla se sigue con los hints y se reintenta más
+/// tarde desde `spawn_root_priming`.
+async fn prime_at_start(
+    cfg: &RecursorConfig,
+    hints: Vec<SocketAddr>,
+    outbound: &Outbound,
+    timeout: Duration,
+) -> (Vec<SocketAddr>, Priming) {
+    let apply = hints.iter().all(|a| a.port() == 53);
+    let mut priming = Priming {
+        hints: hints.clone(),
+        apply,
+        interval: Duration::from_secs(cfg.priming_interval_secs.max(PRIMING_MIN.as_secs())),
+        state: Mutex::new((hints.clone(), PRIMING_RETRY)),
+    };
+    match priming.prime(outbound, timeout).await {
+        Ok(primed) if apply => {
+            tracing::info!("priming: {} roots (TTL {}s)", primed.addrs.len(), primed.ttl);
+            let wait = priming.next_wait(primed.ttl);
+            *priming.state.get_mut().unwrap() = (primed.addrs.clone(), wait);
+            (primed.addrs, priming)
+        }
+        Ok(primed) => {
+            tracing::info!("priming: roots de laboratorio, sigo con los hints");
+            let wait = priming.next_wait(primed.ttl);
+            priming.state.get_mut().unwrap().1 = wait;
+            (hints, priming)
+        }
+        Err(e) => {
+            tracing::warn!("priming: {e:#}; sigo con los hints");
+            (hints, priming)
+        }
+    }
+}
+
+/// Trust anchors del validador. Sin `trust_anchor_file` ni
+/// `recursor.rfc5011.state_file` la política queda igual (KSK de la raíz que
+/// trae hickory). Con RFC 5011 las claves de la raíz salen del estado
+/// persistido (se siembra con las del archivo o las de hickory) y las de
+/// otras zonas quedan fijas.
+#[cfg(feature = "dnssec")]
+async fn trust_anchors(
+    policy: DnssecPolicy,
+    factory: &Arc<Factory>,
+) -> anyhow::Result<(DnssecPolicy, Option<Rollover>)> {
+    use crate::rfc5011::unix_now;
+    use hickory_proto::dnssec::TrustAnchors;
+
+    let cfg = &factory.cfg;
+    if cfg.rfc5011.state_file.is_none() && cfg.rfc5011.is_set() {
+        tracing::warn!("recursor.rfc5011 sin state_file: no hay rollover automático");
+    }
+    if cfg.trust_anchor_file.is_none() && cfg.rfc5011.state_file.is_none() {
+        return Ok((policy, None));
+    }
+    if !matches!(policy, DnssecPolicy::ValidateWithStaticKey { .. }) {
+        anyhow::bail!("recursor.trust_anchor_file y recursor.rfc5011 requieren dnssec = \"validate\"");
+    }
+
+    let mut keys = match &cfg.trust_anchor_file {
+        Some(path) => load_trust_anchor_file(path, factory).await?,
+        None => Vec::new(),
+    };
+    if !keys.iter().any(|(n, _)| n.is_root()) {
+        let builtin = TrustAnchors::default();
+        keys.extend(
+            (0..builtin.len())
+                .filter_map(|i| builtin.get(i))
+                .map(|k| (Name::root(), DNSKEY::with_flags(257, k.clone()))),
+        );
+    }
+    let (root, others): (Vec<_>, Vec<_>) = keys.into_iter().partition(|(n, _)| n.is_root());
+    let root: Vec<DNSKEY> = root.into_iter().map(|(_, k)| k).collect();
+    let others: Vec<DNSKEY> = others.into_iter().map(|(_, k)| k).collect();
+
+    let Some(path) = &cfg.rfc5011.state_file else {
+        let anchors = anchors_of(root.iter().chain(&others));
+        return Ok((validate_with(anchors), None));
+    };
+
+    let path = PathBuf::from(path);
+    let state = if path.exists() {
+        AnchorState::load(&path)?
+    } else {
+        let st = AnchorState::seed(root, unix_now());
+        st.save(&path)?;
+        tracing::info!("RFC 5011: estado inicial con {} claves en {}", st.keys.len(), path.display());
+        st
+    };
+    let state = state.with_hold_down(
+        Duration::from_secs(cfg.rfc5011.add_hold_down_days * 86400),
+        Duration::from_secs(cfg.rfc5011.remove_hold_down_days * 86400),
+    );
+    let rollover = Rollover {
+        factory: factory.clone(),
+        path,
+        refresh: Duration::from_secs(cfg.rfc5011.refresh_secs.max(3600)),
+        others,
+        state: Mutex::new(state),
+    };
+    Ok((validate_with(rollover.anchors()), Some(rollover)))
+}
+
+/// Claves (con dueño) del archivo de anchors. Un recursor sin validar trae el
+/// DNSKEY RRset de los anchors en formato DS.
+#[cfg(feature = "dnssec")]
+async fn load_trust_anchor_file(path: &str, factory: &Factory) -> anyhow::Result<Vec<(Name, DNSKEY)>> {
+    use crate::trust_anchor::AnchorFile;
+    use anyhow::Context;
+    use hickory_proto::op::Query;
+
+    let file = AnchorFile::load(std::path::Path::new(path))?;
+    let mut dnskeys = Vec::new();
+    let owners = file.ds_owners();
+    if !owners.is_empty() {
+        let rec = factory.build(DnssecPolicy::SecurityUnaware)?;
+        for owner in owners {
+            let lookup = rec
+                .resolve(
+                    Query::query(owner.clone(), RecordType::DNSKEY),
+                    Instant::now(),
+                    true,
+                )
+                .await
+                .with_context(|| format!("trust anchor DS {owner}: no pude traer su DNSKEY"))?;
+            dnskeys.push((owner, lookup.records().to_vec()));
+        }
+    }
+
+    let (keys, ds) = (file.keys.len(), file.ds.len());
+    let resolved = file.resolve(&dnskeys)?;
+    tracing::info!(
+        "trust anchors: {} claves ({} DNSKEY + {} DS en {path})",
+        resolved.len(),
+        keys,
+        ds
+    );
+    Ok(resolved)
+}
+
+/// Claves de `policy` para lo que se valida fuera de hickory (el ZONEMD de
+/// la copia de la raíz); sin validación, los KSK de la raíz que trae hickory.
+#[cfg(feature = "dnssec")]
+fn anchor_keys(policy: &DnssecPolicy) -> Vec<DNSKEY> {
+    use hickory_proto::dnssec::TrustAnchors;
+
+    let builtin;
+    let anchors = match policy {
+        DnssecPolicy::ValidateWithStaticKey { trust_anchor: Some(t) } => t.as_ref(),
+        _ => {
+            builtin = TrustAnchors::default();
+            &builtin
+        }
+    };
+    (0..anchors.len())
+        .filter_map(|i| anchors.get(i))
+        .map(|k| DNSKEY::with_flags(257, k.clone()))
+        .collect()
+}
+
+#[cfg(feature = "dnssec")]
+fn anchors_of<'a>(keys: impl Iterator<Item = &'a DNSKEY>) -> hickory_proto::dnssec::TrustAnchors {
+    let mut anchors = hickory_proto::dnssec::TrustAnchors::empty();
+    for key in keys {
+        anchors.insert(key.public_key());
+    }
+    anchors
+}
+
+#[cfg(feature = "dnssec")]
+fn validate_with(anchors: hickory_proto::dnssec::TrustAnchors) -> DnssecPolicy {
+    DnssecPolicy::ValidateWithStaticKey {
+        trust_anchor: Some(Arc::new(anchors)),
+    }
+}
 
-            match timeout(self.timeout, fut).await {
-                Ok(Ok(lookup)) => return Ok(lookup),
-                Ok(Err(e)) => last_err = Some(anyhow::anyhow!(e)),
-                Err(_) => last_err = Some(anyhow::anyhow!("timeout resolviendo")),
+/// Seguimiento RFC 5011 del DNSKEY RRset de la raíz.
+#[cfg(feature = "dnssec")]
+struct Rollover {
+    factory: Arc<Factory>,
+    path: PathBuf,
+    refresh: Duration,
+    /// Anchors de otras zonas (del archivo); no se siguen.
+    others: Vec<DNSKEY>,
+    state: Mutex<AnchorState>,
+}
+
+#[cfg(feature = "dnssec")]
+impl Rollover {
+    fn anchors(&self) -> hickory_proto::dnssec::TrustAnchors {
+        let st = self.state.lock().unwrap();
+        anchors_of(st.trusted().into_iter().chain(&self.others))
+    }
+}
+
+#[cfg(feature = "dnssec")]
+impl RecursorEngine {
+    /// Lanza el refresco por AXFR de la copia de la raíz (si hay fuentes).
+    pub fn spawn_root_mirror_refresh(&self) {
+        if let Some(mirror) = &self.root_mirror {
+            mirror.spawn_refresh();
+        }
+    }
+
+    /// Lanza el refresco periódico RFC 5011 (si hay `recursor.rfc5011.state_file`).
+    pub fn spawn_anchor_rollover(&self) {
+        let Some(rollover) = self.rollover.clone() else {
+            return;
+        };
+        let engine = self.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(rollover.refresh);
+            loop {
+                tick.tick().await;
+                if let Err(e) = engine.refresh_anchors(&rollover).await {
+                    tracing::warn!("RFC 5011: {e:#}");
+                }
+            }
+        });
+    }
+
+    async fn refresh_anchors(&self, rollover: &Rollover) -> anyhow::Result<()> {
+        use crate::rfc5011::unix_now;
+        use anyhow::Context;
+        use hickory_proto::op::Query;
+
+        // Recursor nuevo sin validar: el cache del de servicio devolvería el
+        // RRset viejo hasta que venza su TTL.
+        let rec = rollover.factory.build(unchecked_policy())?;
+        let lookup = rec
+            .resolve(Query::query(Name::root(), RecordType::DNSKEY), Instant::now(), true)
+            .await
+            .context("no pude traer el DNSKEY de la raíz")?;
+
+        let changes = {
+            let mut st = rollover.state.lock().unwrap();
+            let changes = st.observe(lookup.records(), unix_now())?;
+            if changes.state {
+                st.save(&rollover.path)?;
             }
+            changes
+        };
+
+        if changes.trusted {
+            let anchors = rollover.anchors();
+            tracing::info!("RFC 5011: trust anchors de la raíz actualizados ({} claves)", anchors.len());
+            let policy = validate_with(anchors);
+            if let Some(mirror) = &self.root_mirror {
+                mirror.set_anchors(anchor_keys(&policy));
+            }
+            let rec = rollover.factory.build(policy)?;
+            *self.recursor.write().unwrap() = Arc::new(rec);
         }
+        Ok(())
+    }
+}
+
+fn is_validating(policy: &DnssecPolicy) -> bool {
+    #[cfg(feature = "dnssec")]
+    {
+        matches!(policy, DnssecPolicy::ValidateWithStaticKey { .. })
+    }
+    #[cfg(not(feature = "dnssec"))]
+    {
+        let _ = policy;
+        false
+    }
+}
 
-        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("fallo resolviendo")))
+/// Política del recursor para CD=1: pide DNSSEC pero no valida.
+fn unchecked_policy() -> DnssecPolicy {
+    #[cfg(feature = "dnssec")]
+    {
+        DnssecPolicy::ValidationDisabled
+    }
+    #[cfg(not(feature = "dnssec"))]
+    {
+        DnssecPolicy::SecurityUnaware
     }
 }
 
@@ -0,0 +1,87 @@
+//! Política única de reintentos y deadline para una resolución (forwarder y
+//! recursor).
+//!
+//! Cada consulta de cliente tiene un presupuesto total (`deadline`) que cubre
+//! todos los intentos y las esperas entre ellos. Sólo se reintenta lo que el
+//! llamador marca como reintentable (timeouts, SERVFAIL, errores de red);
+//! NXDOMAIN/NODATA son respuestas y cortan en el primer intento.
+
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::time::Instant;
+
+use crate::config::ResolutionConfig;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub deadline: Duration,
+    /// Intentos totales (el primero incluido).
+    pub attempts: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Fracción de jitter aplicada al backoff (0.2 = ±20%).
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    pub fn from_config(cfg: &ResolutionConfig) -> Self {
+        Self {
+            deadline: Duration::from_millis(cfg.deadline_ms.max(1)),
+            attempts: cfg.attempts.max(1),
+            backoff_base: Duration::from_millis(cfg.backoff_base_ms),
+            backoff_max: Duration::from_millis(cfg.backoff_max_ms),
+            jitter: cfg.jitter,
+        }
+    }
+
+    /// Espera antes del reintento `n` (0 = el primero): base * 2^n, con tope
+    /// y jitter.
+    pub fn backoff(&self, n: u32) -> Duration {
+        let exp = self
+            .backoff_base
+            .saturating_mul(1u32 << n.min(16))
+            .min(self.backoff_max);
+        jittered(exp, self.jitter)
+    }
+
+    /// Corre `op` hasta que dé un resultado no reintentable, se acaben los
+    /// intentos o no quede presupuesto para esperar y reintentar. Si el
+    /// deadline vence con un intento en curso, éste se cancela y se devuelve
+    /// `expired()`.
+    pub async fn run<T, F, Fut>(&self, mut op: F, retryable: impl Fn(&T) -> bool, expired: impl FnOnce() -> T) -> T
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let deadline = Instant::now() + self.deadline;
+        let mut n = 0;
+        loop {
+            let res = match tokio::time::timeout_at(deadline, op()).await {
+                Ok(res) => res,
+                Err(_) => return expired(),
+            };
+            n += 1;
+            if n >= self.attempts || !retryable(&res) {
+                return res;
+            }
+
+            let wait = self.backoff(n - 1);
+            if Instant::now() + wait >= deadline {
+                return res;
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// `d` con ±`jitter` (fracción, acotada a 0..=1).
+pub fn jittered(d: Duration, jitter: f64) -> Duration {
+    let j = jitter.clamp(0.0, 1.0);
+    if j == 0.0 {
+        return d;
+    }
+    let factor = rand::rng().random_range((1.0 - j)..=(1.0 + j));
+    d.mul_f64(factor)
+}
@@ -0,0 +1,319 @@
+//! Rollover automático del trust anchor de la raíz (RFC 5011).
+//!
+//! Se sigue el DNSKEY RRset de la raíz y cada KSK pasa por los estados del
+//! RFC 5011 §4:
+//!
+//! - `AddPend`: clave nueva vista en un RRset firmado por un anchor confiable;
+//!   se acepta recién después del add hold-down (30 días) visto sin cortes.
+//! - `Valid`: anchor confiable.
+//! - `Missing`: anchor que dejó de aparecer en el RRset; sigue siendo confiable.
+//! - `Revoked`: la clave apareció con el bit REVOKE y autofirmada; deja de ser
+//!   confiable para siempre y se olvida pasado el remove hold-down.
+//!
+//! El estado se guarda en formato de zona con los metadatos en comentarios,
+//! escrito de forma atómica:
+//!
+//! ```text
+//! . IN DNSKEY 257 3 8 AwEAAaz/tAm8yTn4Mfeh... ; state=VALID first_seen=1700000000 last_change=1700000000
+//! ```
+
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use hickory_proto::dnssec::rdata::DNSKEY;
+use hickory_proto::dnssec::PublicKey;
+use hickory_proto::rr::{Name, Record, RecordType};
+
+use crate::atomic_file::write_atomic;
+use crate::trust_anchor::{dnskey_of, dnskey_rrset_signed_by, parse_dnskey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    AddPend,
+    Valid,
+    Missing,
+    Revoked,
+}
+
+impl KeyState {
+    pub fn is_trusted(self) -> bool {
+        matches!(self, Self::Valid | Self::Missing)
+    }
+
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "ADDPEND" => Ok(Self::AddPend),
+            "VALID" => Ok(Self::Valid),
+            "MISSING" => Ok(Self::Missing),
+            "REVOKED" => Ok(Self::Revoked),
+            _ => anyhow::bail!("estado desconocido: {s}"),
+        }
+    }
+}
+
+impl fmt::Display for KeyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::AddPend => "ADDPEND",
+            Self::Valid => "VALID",
+            Self::Missing => "MISSING",
+            Self::Revoked => "REVOKED",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackedKey {
+    /// La clave tal como se vio por última vez (con REVOKE si está revocada).
+    pub key: DNSKEY,
+    pub state: KeyState,
+    /// Segundos unix: primera vez que se vio (arranque del add hold-down).
+    pub first_seen: u64,
+    pub last_change: u64,
+}
+
+impl TrackedKey {
+    /// Misma clave, ignorando el bit REVOKE (que cambia flags y key tag).
+    fn same_key(&self, other: &DNSKEY) -> bool {
+        let (a, b) = (self.key.public_key(), other.public_key());
+        a.algorithm() == b.algorithm() && a.public_bytes() == b.public_bytes()
+    }
+
+    fn set_state(&mut self, state: KeyState, now: u64) {
+        tracing::info!(
+            "RFC 5011: clave {} {} -> {state}",
+            key_tag(&self.key),
+            self.state
+        );
+        self.state = state;
+        self.last_change = now;
+    }
+}
+
+/// Estado de los anchors de la raíz.
+#[derive(Debug, Clone)]
+pub struct AnchorState {
+    pub keys: Vec<TrackedKey>,
+    add_hold_down: u64,
+    remove_hold_down: u64,
+}
+
+/// Qué cambió al procesar un DNSKEY RRset.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Changes {
+    /// Cambió algún estado (hay que persistir).
+    pub state: bool,
+    /// Cambió el conjunto de claves confiables (hay que rearmar el validador).
+    pub trusted: bool,
+}
+
+impl AnchorState {
+    /// Estado inicial: todas las claves confiables.
+    pub fn seed(keys: impl IntoIterator<Item = DNSKEY>, now: u64) -> Self {
+        let mut st = Self::empty();
+        for key in keys {
+            if key.revoke() || st.keys.iter().any(|t| t.same_key(&key)) {
+                continue;
+            }
+            st.keys.push(TrackedKey {
+                key,
+                state: KeyState::Valid,
+                first_seen: now,
+                last_change: now,
+            });
+        }
+        st
+    }
+
+    fn empty() -> Self {
+        Self {
+            keys: Vec::new(),
+            add_hold_down: 30 * 86400,
+            remove_hold_down: 30 * 86400,
+        }
+    }
+
+    pub fn with_hold_down(mut self, add: Duration, remove: Duration) -> Self {
+        self.add_hold_down = add.as_secs();
+        self.remove_hold_down = remove.as_secs();
+        self
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let txt = std::fs::read_to_string(path)
+            .with_context(|| format!("no pude leer estado RFC 5011: {}", path.display()))?;
+        Self::parse(&txt).with_context(|| format!("estado RFC 5011 inválido: {}", path.display()))
+    }
+
+    pub fn parse(txt: &str) -> anyhow::Result<Self> {
+        let mut st = Self::empty();
+        for (i, line) in txt.lines().enumerate() {
+            let (record, meta) = line.split_once(';').unwrap_or((line, ""));
+            if record.trim().is_empty() {
+                continue;
+            }
+            let key = parse_line(record, meta).with_context(|| format!("línea {}: {line}", i + 1))?;
+            st.keys.push(key);
+        }
+        if !st.keys.iter().any(|k| k.state.is_trusted()) {
+            anyhow::bail!("no queda ninguna clave confiable");
+        }
+        Ok(st)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::from("; Estado RFC 5011 de los trust anchors de la raíz (lo reescribe el recursor)\n");
+        for k in &self.keys {
+            out.push_str(&format!(
+                ". IN DNSKEY {} ; state={} first_seen={} last_change={}\n",
+                k.key, k.state, k.first_seen, k.last_change
+            ));
+        }
+        out
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        write_atomic(path, self.to_text().as_bytes())
+    }
+
+    /// Claves con las que se valida (`Valid` y `Missing`).
+    pub fn trusted(&self) -> Vec<&DNSKEY> {
+        self.keys
+            .iter()
+            .filter(|k| k.state.is_trusted())
+            .map(|k| &k.key)
+            .collect()
+    }
+
+    /// Procesa la respuesta a `. DNSKEY` (DNSKEY + RRSIG). Si el RRset no
+    /// está firmado por un anchor confiable no se toca nada (RFC 5011 §2.2).
+    pub fn observe(&mut self, records: &[Record], now: u64) -> anyhow::Result<Changes> {
+        let root = Name::root();
+        let seen: Vec<&DNSKEY> = records
+            .iter()
+            .filter(|r| r.record_type() == RecordType::DNSKEY && r.name().is_root())
+            .filter_map(dnskey_of)
+            .filter(|k| k.zone_key() && k.secure_entry_point())
+            .collect();
+
+        let trusted = self.trusted();
+        let signers: Vec<&DNSKEY> = trusted.into_iter().filter(|k| !k.revoke()).collect();
+        if !dnskey_rrset_signed_by(&root, &signers, records, Some(now)) {
+            anyhow::bail!("el DNSKEY RRset de la raíz no está firmado por un trust anchor vigente");
+        }
+
+        let before: Vec<DNSKEY> = self.trusted().into_iter().cloned().collect();
+        let mut changes = Changes::default();
+
+        for key in &seen {
+            match self.keys.iter_mut().find(|t| t.same_key(key)) {
+                Some(t) if key.revoke() => {
+                    // Sólo vale la revocación autofirmada (RFC 5011 §2.1).
+                    let self_signed = dnskey_rrset_signed_by(&root, &[*key], records, Some(now));
+                    if t.state != KeyState::Revoked && self_signed {
+                        t.key = (*key).clone();
+                        t.set_state(KeyState::Revoked, now);
+                        changes.state = true;
+                    }
+                }
+                Some(t) => match t.state {
+                    KeyState::AddPend if now.saturating_sub(t.first_seen) >= self.add_hold_down => {
+                        t.set_state(KeyState::Valid, now);
+                        changes.state = true;
+                    }
+                    KeyState::Missing => {
+                        t.set_state(KeyState::Valid, now);
+                        changes.state = true;
+                    }
+                    _ => {}
+                },
+                None if !key.revoke() => {
+                    tracing::info!("RFC 5011: clave nueva {} -> ADDPEND", key_tag(key));
+                    self.keys.push(TrackedKey {
+                        key: (*key).clone(),
+                        state: KeyState::AddPend,
+                        first_seen: now,
+                        last_change: now,
+                    });
+                    changes.state = true;
+                }
+                None => {}
+            }
+        }
+
+        let remove_hold_down = self.remove_hold_down;
+        let mut dropped = false;
+        self.keys.retain_mut(|t| {
+            if seen.iter().any(|k| t.same_key(k)) {
+                return true;
+            }
+            let keep = match t.state {
+                KeyState::Valid => {
+                    t.set_state(KeyState::Missing, now);
+                    changes.state = true;
+                    true
+                }
+                // Una clave pendiente que desaparece vuelve a empezar.
+                KeyState::AddPend => false,
+                KeyState::Revoked => now.saturating_sub(t.last_change) < remove_hold_down,
+                KeyState::Missing => true,
+            };
+            dropped |= !keep;
+            keep
+        });
+        changes.state |= dropped;
+
+        let after = self.trusted();
+        changes.trusted = after.len() != before.len() || before.iter().any(|k| !after.contains(&k));
+        if after.is_empty() {
+            tracing::error!("RFC 5011: no queda ningún trust anchor de la raíz confiable");
+        }
+        Ok(changes)
+    }
+}
+
+fn parse_line(record: &str, meta: &str) -> anyhow::Result<TrackedKey> {
+    let tokens: Vec<&str> = record.split_whitespace().collect();
+    if tokens.first() != Some(&".") {
+        anyhow::bail!("sólo se siguen claves de la raíz");
+    }
+    let type_at = tokens
+        .iter()
+        .position(|t| t.eq_ignore_ascii_case("DNSKEY"))
+        .context("se esperaba un registro DNSKEY")?;
+    let key = parse_dnskey(&Name::root(), &tokens[type_at + 1..])?;
+
+    let mut state = None;
+    let (mut first_seen, mut last_change) = (None, None);
+    for field in meta.split_whitespace() {
+        match field.split_once('=') {
+            Some(("state", v)) => state = Some(KeyState::parse(v)?),
+            Some(("first_seen", v)) => first_seen = Some(v.parse::<u64>().context("first_seen")?),
+            Some(("last_change", v)) => last_change = Some(v.parse::<u64>().context("last_change")?),
+            _ => {}
+        }
+    }
+    let first_seen = first_seen.context("falta first_seen")?;
+    Ok(TrackedKey {
+        key,
+        state: state.context("falta state")?,
+        first_seen,
+        last_change: last_change.unwrap_or(first_seen),
+    })
+}
+
+fn key_tag(key: &DNSKEY) -> String {
+    key.calculate_key_tag()
+        .map(|t| t.to_string())
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,736 @@
+//! Copia local de la zona raíz (RFC 8806).
+//!
+//! La zona se carga de un archivo (y opcionalmente se refresca por AXFR),
+//! se verifica su ZONEMD (RFC 8976: el digest y la firma del RRset, con el
+//! DNSKEY de la zona firmado por un trust anchor) y se sirve en loopback; el recursor la
+//! usa como único root, así las referencias a los TLD salen de la copia en
+//! lugar de los root servers. Si la copia vence (expire de la SOA sin
+//! refresh exitoso) el servidor local reenvía a los roots reales.
+//!
+//! Requiere `--features dnssec` (ZONEMD, registros DNSSEC de la zona). Con
+//! `dnssec = "validate"` el recursor además valida todo lo que sale de la
+//! copia contra los trust anchors, igual que con los roots.
+
+use std::collections::BTreeMap;
+use std::iter;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Context;
+use hickory_proto::dnssec::rdata::{DNSSECRData, DNSKEY, NSEC, RRSIG};
+use hickory_proto::dnssec::Algorithm;
+use hickory_proto::op::{Edns, Header, Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::rdata::NULL;
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_proto::serialize::txt::RDataParser;
+use hickory_server::authority::MessageResponseBuilder;
+use hickory_server::server::{Request, RequestHandler, ResponseHandler, ResponseInfo};
+
+use crate::config::RootMirrorConfig;
+use crate::exchange;
+use crate::outbound::Outbound;
+use crate::rfc5011::unix_now;
+use crate::trust_anchor::{dnskey_of, dnskey_rrset_signed_by, parse_dnskey, rrset_signed_by};
+
+/// ZONEMD (RFC 8976): hickory 0.25 no lo conoce.
+pub const ZONEMD: RecordType = RecordType::Unknown(63);
+
+/// Sin `refresh_secs`, cada cuánto se revisa como máximo la SOA de las fuentes.
+const MAX_REFRESH: Duration = Duration::from_secs(86400);
+const AXFR_TIMEOUT: Duration = Duration::from_secs(120);
+const EDNS_MAX_PAYLOAD: u16 = 1232;
+
+/// Respuesta armada desde la zona.
+#[derive(Debug)]
+pub struct Reply {
+    pub rcode: ResponseCode,
+    pub authoritative: bool,
+    pub answers: Vec<Record>,
+    pub authority: Vec<Record>,
+    pub additionals: Vec<Record>,
+}
+
+impl Reply {
+    fn new(rcode: ResponseCode, authoritative: bool) -> Self {
+        Self {
+            rcode,
+            authoritative,
+            answers: Vec::new(),
+            authority: Vec::new(),
+            additionals: Vec::new(),
+        }
+    }
+}
+
+pub struct RootZone {
+    pub serial: u32,
+    pub refresh: Duration,
+    pub expire: Duration,
+    /// Registros por dueño (en minúsculas), en orden canónico.
+    names: BTreeMap<Name, Vec<Record>>,
+}
+
+impl RootZone {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let txt = std::fs::read_to_string(path).with_context(|| format!("no pude leer {}", path.display()))?;
+        Self::parse(&txt).with_context(|| format!("zona raíz inválida: {}", path.display()))
+    }
+
+    /// Un registro por línea (`dueño TTL IN tipo rdata`), como el archivo de
+    /// IANA o la salida de `dig axfr . @<fuente>`.
+    pub fn parse(txt: &str) -> anyhow::Result<Self> {
+        let mut records = Vec::new();
+        for (n, line) in txt.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            records.push(parse_line(line).with_context(|| format!("línea {}", n + 1))?);
+        }
+        Self::from_records(records)
+    }
+
+    pub fn from_records(records: Vec<Record>) -> anyhow::Result<Self> {
+        let mut names: BTreeMap<Name, Vec<Record>> = BTreeMap::new();
+        for r in records {
+            if r.dns_class() != DNSClass::IN {
+                continue;
+            }
+            if !names.get(&r.name().to_lowercase()).is_some_and(|rs| rs.contains(&r)) {
+                names.entry(r.name().to_lowercase()).or_default().push(r);
+            }
+        }
+        let soa = names
+            .get(&Name::root())
+            .and_then(|rs| rs.iter().find_map(|r| r.data().as_soa()))
+            .context("la zona no tiene SOA en la raíz")?;
+        let (serial, refresh, expire) = (soa.serial(), soa.refresh(), soa.expire());
+        if !names.values().flatten().any(|r| r.record_type() == RecordType::NS && r.name().is_root()) {
+            anyhow::bail!("la zona no tiene NS en la raíz");
+        }
+        Ok(Self {
+            serial,
+            refresh: Duration::from_secs(refresh.max(0) as u64),
+            expire: Duration::from_secs(expire.max(0) as u64),
+            names,
+        })
+    }
+
+    /// Cantidad de registros de la zona.
+    pub fn record_count(&self) -> usize {
+        self.names.values().map(Vec::len).sum()
+    }
+
+    /// Verifica el ZONEMD de la zona (esquema SIMPLE, SHA-384).
+    pub fn verify_zonemd(&self) -> anyhow::Result<()> {
+        let zonemds: Vec<&[u8]> = self
+            .rrset(&Name::root(), ZONEMD)
+            .filter_map(|r| match r.data() {
+                RData::Unknown { rdata, .. } => Some(rdata.anything()),
+                _ => None,
+            })
+            .collect();
+        if zonemds.is_empty() {
+            anyhow::bail!("la zona no tiene ZONEMD");
+        }
+        let usable: Vec<&[u8]> = zonemds
+            .into_iter()
+            .filter(|z| z.len() > 6 && z[..4] == self.serial.to_be_bytes() && z[4] == 1 && z[5] == 1)
+            .collect();
+        if usable.is_empty() {
+            anyhow::bail!("ningún ZONEMD SIMPLE/SHA-384 para el serial {}", self.serial);
+        }
+        let digest = self.zonemd_digest()?;
+        if !usable.iter().any(|z| z[6..] == digest[..]) {
+            anyhow::bail!("el ZONEMD no coincide con el contenido de la zona (serial {})", self.serial);
+        }
+        Ok(())
+    }
+
+    /// RFC 8976 §4: con la zona firmada el ZONEMD sólo vale si su RRSIG la
+    /// hizo una clave del DNSKEY RRset del apex y ese RRset está firmado por
+    /// uno de `anchors`. `now` en segundos unix.
+    pub fn verify_zonemd_signature(&self, anchors: &[DNSKEY], now: u64) -> anyhow::Result<()> {
+        let root = Name::root();
+        let dnskeys = self.rrset_signed(&root, RecordType::DNSKEY, true);
+        let anchors: Vec<&DNSKEY> = anchors.iter().collect();
+        if !dnskey_rrset_signed_by(&root, &anchors, &dnskeys, Some(now)) {
+            anyhow::bail!("el DNSKEY de la zona no está firmado por un trust anchor vigente");
+        }
+        let keys: Vec<&DNSKEY> = dnskeys
+            .iter()
+            .filter_map(dnskey_of)
+            .filter(|k| k.zone_key() && !k.revoke())
+            .collect();
+        let zonemd = self.rrset_signed(&root, ZONEMD, true);
+        if !rrset_signed_by(&root, ZONEMD, &keys, &zonemd, Some(now)) {
+            anyhow::bail!("el ZONEMD no tiene una firma vigente del DNSKEY de la zona");
+        }
+        Ok(())
+    }
+
+    /// RFC 8976 §3.3: RRs en forma y orden canónicos, sin el ZONEMD del apex
+    /// ni sus firmas.
+    fn zonemd_digest(&self) -> anyhow::Result<Vec<u8>> {
+        use hickory_proto::dnssec::crypto::Digest;
+        use hickory_proto::dnssec::DigestType;
+        use hickory_proto::serialize::binary::{BinEncodable, BinEncoder};
+
+        let mut rrs = Vec::with_capacity(self.record_count());
+        for r in self.names.values().flatten() {
+            if r.name().is_root() && (r.record_type() == ZONEMD || covered_type(r) == Some(ZONEMD)) {
+                continue;
+            }
+            let mut rdata = Vec::new();
+            {
+                let mut encoder = BinEncoder::new(&mut rdata);
+                encoder.set_canonical_names(true);
+                r.data().emit(&mut encoder)?;
+            }
+            rrs.push((r.name().to_lowercase(), u16::from(r.record_type()), rdata, r));
+        }
+        rrs.sort_by(|a, b| (&a.0, a.1, &a.2).cmp(&(&b.0, b.1, &b.2)));
+        rrs.dedup_by(|a, b| (&a.0, a.1, &a.2) == (&b.0, b.1, &b.2));
+
+        let mut buf = Vec::new();
+        {
+            let mut encoder = BinEncoder::new(&mut buf);
+            encoder.set_canonical_names(true);
+            for (name, rtype, rdata, r) in &rrs {
+                name.emit_as_canonical(&mut encoder, true)?;
+                encoder.emit_u16(*rtype)?;
+                r.dns_class().emit(&mut encoder)?;
+                encoder.emit_u32(r.ttl())?;
+                encoder.emit_u16(rdata.len() as u16)?;
+                encoder.emit_vec(rdata)?;
+            }
+        }
+        Ok(Digest::new(&buf, DigestType::SHA384)?.as_ref().to_vec())
+    }
+
+    fn rrset<'a>(&'a self, name: &Name, rtype: RecordType) -> impl Iterator<Item = &'a Record> {
+        self.names
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(move |r| r.record_type() == rtype)
+    }
+
+    /// El RRset y, con DO, sus RRSIG.
+    fn rrset_signed(&self, name: &Name, rtype: RecordType, dnssec_ok: bool) -> Vec<Record> {
+        let sigs = self
+            .rrset(name, RecordType::RRSIG)
+            .filter(move |r| dnssec_ok && covered_type(r) == Some(rtype));
+        self.rrset(name, rtype).chain(sigs).cloned().collect()
+    }
+
+    /// NSEC cuyo rango contiene `name` (el último de la cadena da la vuelta).
+    fn nsec_covering(&self, name: &Name) -> Option<&Name> {
+        self.names
+            .range(..=name.clone())
+            .rev()
+            .find(|(_, rs)| rs.iter().any(|r| r.record_type() == RecordType::NSEC))
+            .map(|(owner, _)| owner)
+    }
+
+    /// Respuesta autoritativa para `qname`/`qtype`, o la referencia al TLD.
+    pub fn lookup(&self, qname: &Name, qtype: RecordType, dnssec_ok: bool) -> Reply {
+        let qname = qname.to_lowercase();
+        let mut reply = Reply::new(ResponseCode::NoError, true);
+
+        if !qname.is_root() {
+            let tld = qname.trim_to(1);
+            let delegated = self.rrset(&tld, RecordType::NS).next().is_some();
+            if !delegated {
+                reply.rcode = ResponseCode::NXDomain;
+                reply.authority = self.negative(&qname, dnssec_ok);
+                return reply;
+            }
+            if !(qname == tld && qtype == RecordType::DS) {
+                reply.authoritative = false;
+                reply.authority = self.rrset(&tld, RecordType::NS).cloned().collect();
+                if dnssec_ok {
+                    // DS firmado, o el NSEC que prueba que no hay DS.
+                    let ds = self.rrset_signed(&tld, RecordType::DS, true);
+                    let proof = if ds.is_empty() {
+                        self.rrset_signed(&tld, RecordType::NSEC, true)
+                    } else {
+                        ds
+                    };
+                    reply.authority.extend(proof);
+                }
+                reply.additionals = self.glue(&reply.authority);
+                return reply;
+            }
+        }
+
+        reply.answers = self.rrset_signed(&qname, qtype, dnssec_ok);
+        if reply.answers.is_empty() {
+            reply.authority = self.rrset_signed(&Name::root(), RecordType::SOA, dnssec_ok);
+            if dnssec_ok {
+                reply.authority.extend(self.rrset_signed(&qname, RecordType::NSEC, true));
+            }
+        }
+        reply
+    }
+
+    /// SOA y, con DO, los NSEC que cubren el nombre y el comodín `*.`.
+    fn negative(&self, qname: &Name, dnssec_ok: bool) -> Vec<Record> {
+        let mut out = self.rrset_signed(&Name::root(), RecordType::SOA, dnssec_ok);
+        if !dnssec_ok {
+            return out;
+        }
+        let wildcard = Name::root().prepend_label("*").ok();
+        let owners = [Some(qname), wildcard.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|n| self.nsec_covering(n).cloned())
+            .collect::<Vec<_>>();
+        for owner in owners {
+            for r in self.rrset_signed(&owner, RecordType::NSEC, true) {
+                if !out.contains(&r) {
+                    out.push(r);
+                }
+            }
+        }
+        out
+    }
+
+    /// A/AAAA de la zona para los NS de una referencia.
+    fn glue(&self, authority: &[Record]) -> Vec<Record> {
+        authority
+            .iter()
+            .filter_map(|r| r.data().as_ns())
+            .flat_map(|ns| {
+                let ns = ns.0.to_lowercase();
+                let v4 = self.rrset(&ns, RecordType::A);
+                let v6 = self.rrset(&ns, RecordType::AAAA);
+                v4.chain(v6).cloned().collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+fn covered_type(r: &Record) -> Option<RecordType> {
+    match r.data() {
+        RData::DNSSEC(DNSSECRData::RRSIG(sig)) => Some(sig.type_covered()),
+        _ => None,
+    }
+}
+
+/// hickory 0.25 no parsea en texto DNSKEY, NSEC ni RRSIG (ni conoce
+/// ZONEMD): esos se arman acá.
+fn parse_line(line: &str) -> anyhow::Result<Record> {
+    let mut tokens = line.split_whitespace();
+    let owner = Name::from_ascii(tokens.next().context("falta el dueño")?)?;
+    let (mut ttl, mut rtype) = (None, None);
+    for t in tokens.by_ref() {
+        if let Ok(v) = t.parse::<u32>() {
+            ttl = Some(v);
+        } else if !t.eq_ignore_ascii_case("IN") {
+            rtype = Some(t.to_ascii_uppercase());
+            break;
+        }
+    }
+    let ttl = ttl.context("falta el TTL")?;
+    let rtype = rtype.context("falta el tipo")?;
+    let rdata: Vec<&str> = tokens.collect();
+
+    let data = match rtype.as_str() {
+        "ZONEMD" => zonemd_rdata(&rdata)?,
+        "NSEC" => nsec_rdata(&rdata)?,
+        "RRSIG" => rrsig_rdata(&rdata)?,
+        "DNSKEY" => RData::DNSSEC(DNSSECRData::DNSKEY(parse_dnskey(&owner, &rdata)?)),
+        _ => RData::parse(RecordType::from_str(&rtype)?, rdata.iter().copied(), Some(&Name::root()))?,
+    };
+    Ok(Record::from_rdata(owner, ttl, data))
+}
+
+fn record_type(t: &str) -> anyhow::Result<RecordType> {
+    if t.eq_ignore_ascii_case("ZONEMD") {
+        return Ok(ZONEMD);
+    }
+    RecordType::from_str(t).with_context(|| format!("tipo desconocido: {t}"))
+}
+
+/// `serial esquema algoritmo digest-hex`.
+fn zonemd_rdata(tokens: &[&str]) -> anyhow::Result<RData> {
+    let [serial, scheme, alg, digest @ ..] = tokens else {
+        anyhow::bail!("ZONEMD incompleto");
+    };
+    let mut bytes = serial.parse::<u32>()?.to_be_bytes().to_vec();
+    bytes.push(scheme.parse()?);
+    bytes.push(alg.parse()?);
+    let hex = digest.concat();
+    if hex.len() % 2 != 0 {
+        anyhow::bail!("digest ZONEMD inválido");
+    }
+    for i in (0..hex.len()).step_by(2) {
+        bytes.push(u8::from_str_radix(&hex[i..i + 2], 16).context("digest ZONEMD inválido")?);
+    }
+    Ok(RData::Unknown {
+        code: ZONEMD,
+        rdata: NULL::with(bytes),
+    })
+}
+
+/// `siguiente tipo...`.
+fn nsec_rdata(tokens: &[&str]) -> anyhow::Result<RData> {
+    let [next, types @ ..] = tokens else {
+        anyhow::bail!("NSEC incompleto");
+    };
+    let types = types.iter().map(|t| record_type(t)).collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(RData::DNSSEC(DNSSECRData::NSEC(NSEC::new(Name::from_ascii(next)?, types))))
+}
+
+/// `tipo algoritmo etiquetas ttl expiración inicio key-tag firmante firma-base64`.
+fn rrsig_rdata(tokens: &[&str]) -> anyhow::Result<RData> {
+    let [covered, alg, labels, ttl, expiration, inception, tag, signer, sig @ ..] = tokens else {
+        anyhow::bail!("RRSIG incompleto");
+    };
+    let sig = data_encoding::BASE64
+        .decode(sig.concat().as_bytes())
+        .context("firma RRSIG inválida")?;
+    Ok(RData::DNSSEC(DNSSECRData::RRSIG(RRSIG::new(
+        record_type(covered)?,
+        Algorithm::from_u8(alg.parse()?),
+        labels.parse()?,
+        ttl.parse()?,
+        sig_time(expiration)?,
+        sig_time(inception)?,
+        tag.parse()?,
+        Name::from_ascii(signer)?,
+        sig,
+    ))))
+}
+
+/// RFC 4034 §3.2: `YYYYMMDDHHmmSS` (UTC) o segundos unix.
+fn sig_time(t: &str) -> anyhow::Result<u32> {
+    if t.len() != 14 || !t.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(t.parse()?);
+    }
+    let field = |r: std::ops::Range<usize>| t[r].parse::<i64>().with_context(|| format!("fecha inválida: {t}"));
+    let (y, m, d) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    // Días desde 1970-01-01 (algoritmo days_from_civil de H. Hinnant).
+    let (y, m) = if m <= 2 { (y - 1, m + 9) } else { (y, m - 3) };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + d - 1;
+    let days = era * 146097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719468;
+    let secs = days * 86400 + field(8..10)? * 3600 + field(10..12)? * 60 + field(12..14)?;
+    // Aritmética de números de serie (RFC 4034 §3.1.5): módulo 2^32.
+    Ok(secs as u32)
+}
+
+/// RFC 1982: ¿`a` es posterior a `b`?
+fn serial_newer(a: u32, b: u32) -> bool {
+    a != b && (a.wrapping_sub(b) as i32) > 0
+}
+
+struct Loaded {
+    zone: Arc<RootZone>,
+    /// Vence si pasa el expire de la SOA sin un refresh exitoso.
+    expires: Instant,
+}
+
+pub struct RootMirror {
+    cfg: RootMirrorConfig,
+    current: RwLock<Option<Loaded>>,
+    sources: Vec<SocketAddr>,
+    /// Roots reales, para cuando la copia vence.
+    roots: Vec<SocketAddr>,
+    outbound: Outbound,
+    timeout: Duration,
+    /// Claves de la raíz con las que se valida la firma del ZONEMD.
+    anchors: RwLock<Vec<DNSKEY>>,
+}
+
+impl RootMirror {
+    /// Sin copia cargada (ver `load`) el servidor local reenvía a los roots.
+    pub fn new(
+        cfg: &RootMirrorConfig,
+        roots: Vec<SocketAddr>,
+        outbound: Outbound,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let sources = crate::roots::load(&cfg.axfr_sources).context("recursor.root_mirror.axfr_sources")?;
+        Ok(Self {
+            cfg: cfg.clone(),
+            current: RwLock::new(None),
+            sources,
+            roots,
+            outbound,
+            timeout,
+            anchors: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Trust anchors de la raíz; se actualizan con los rollovers (RFC 5011).
+    pub fn set_anchors(&self, keys: Vec<DNSKEY>) {
+        *self.anchors.write().unwrap() = keys;
+    }
+
+    /// Carga la copia del archivo. Una copia inválida no impide arrancar si
+    /// hay fuentes AXFR: mientras tanto se reenvía a los roots.
+    pub fn load(&self) -> anyhow::Result<()> {
+        match self.load_file() {
+            Ok(()) => Ok(()),
+            Err(e) if !self.sources.is_empty() => {
+                tracing::warn!("root mirror: {e:#}; espero el AXFR");
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn load_file(&self) -> anyhow::Result<()> {
+        let path = Path::new(&self.cfg.file);
+        let zone = RootZone::load(path)?;
+        self.check(&zone)?;
+        // El expire corre desde que se bajó la copia, no desde que arrancamos.
+        let age = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| SystemTime::now().duration_since(t).ok())
+            .unwrap_or_default();
+        let Some(left) = zone.expire.checked_sub(age) else {
+            anyhow::bail!("la copia de {} ya venció (serial {})", path.display(), zone.serial);
+        };
+        self.install(zone, left);
+        Ok(())
+    }
+
+    fn check(&self, zone: &RootZone) -> anyhow::Result<()> {
+        if self.cfg.require_zonemd {
+            let anchors = self.anchors.read().unwrap().clone();
+            zone.verify_zonemd_signature(&anchors, unix_now())?;
+            zone.verify_zonemd()?;
+        }
+        if let Some(cur) = self.zone() {
+            if !serial_newer(zone.serial, cur.serial) {
+                anyhow::bail!("serial {} no es posterior a {}", zone.serial, cur.serial);
+            }
+        }
+        Ok(())
+    }
+
+    fn install(&self, zone: RootZone, valid_for: Duration) {
+        tracing::info!("root mirror: serial {} ({} registros)", zone.serial, zone.record_count());
+        *self.current.write().unwrap() = Some(Loaded {
+            zone: Arc::new(zone),
+            expires: Instant::now() + valid_for,
+        });
+    }
+
+    /// La copia vigente; `None` si no hay o venció.
+    pub fn zone(&self) -> Option<Arc<RootZone>> {
+        let now = Instant::now();
+        self.current
+            .read()
+            .unwrap()
+            .as_ref()
+            .filter(|l| l.expires > now)
+            .map(|l| l.zone.clone())
+    }
+
+    pub fn status_line(&self) -> String {
+        let now = Instant::now();
+        match self.current.read().unwrap().as_ref() {
+            Some(l) if l.expires > now => format!(
+                "root_mirror serial={} vence_en={}s",
+                l.zone.serial,
+                (l.expires - now).as_secs()
+            ),
+            Some(l) => format!("root_mirror serial={} vencida (reenvío a los roots)", l.zone.serial),
+            None => "root_mirror sin copia (reenvío a los roots)".to_string(),
+        }
+    }
+
+    /// Sirve la copia en `listen` (UDP y TCP) para el recursor.
+    pub async fn serve(self: &Arc<Self>, listen: SocketAddr) -> anyhow::Result<()> {
+        use hickory_server::ServerFuture;
+        use tokio::net::{TcpListener, UdpSocket};
+
+        let udp = UdpSocket::bind(listen)
+            .await
+            .with_context(|| format!("root mirror: no pude escuchar UDP {listen}"))?;
+        let tcp = TcpListener::bind(listen)
+            .await
+            .with_context(|| format!("root mirror: no pude escuchar TCP {listen}"))?;
+        let mut server = ServerFuture::new(MirrorHandler(self.clone()));
+        server.register_socket(udp);
+        server.register_listener(tcp, Duration::from_secs(10));
+        tokio::spawn(async move {
+            if let Err(e) = server.block_until_done().await {
+                tracing::error!("root mirror: {e}");
+            }
+        });
+        tracing::info!("root mirror en {listen}");
+        Ok(())
+    }
+
+    /// Refresco periódico por AXFR (sin fuentes no hace nada).
+    pub fn spawn_refresh(self: &Arc<Self>) {
+        if self.sources.is_empty() {
+            return;
+        }
+        let this = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let ok = match this.refresh().await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::warn!("root mirror: refresh falló: {e:#}");
+                        false
+                    }
+                };
+                tokio::time::sleep(this.refresh_interval(ok)).await;
+            }
+        });
+    }
+
+    fn refresh_interval(&self, ok: bool) -> Duration {
+        let soa = self.zone().map(|z| (z.refresh, z.expire));
+        let every = self
+            .cfg
+            .refresh_secs
+            .map(Duration::from_secs)
+            .or(soa.map(|(refresh, _)| refresh))
+            .unwrap_or(MAX_REFRESH)
+            .clamp(Duration::from_secs(60), MAX_REFRESH);
+        // Tras una falla se reintenta antes (el retry de la SOA sería lo ideal).
+        if ok {
+            every
+        } else {
+            (every / 4).max(Duration::from_secs(60))
+        }
+    }
+
+    /// Compara el serial de cada fuente y baja la zona si hay una más nueva.
+    pub async fn refresh(&self) -> anyhow::Result<()> {
+        let mut last_err = anyhow::anyhow!("sin fuentes AXFR");
+        for &source in &self.sources {
+            match self.refresh_from(source).await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_err = e.context(format!("fuente {source}")),
+            }
+        }
+        Err(last_err)
+    }
+
+    async fn refresh_from(&self, source: SocketAddr) -> anyhow::Result<()> {
+        let mut msg = Message::new();
+        msg.set_id(rand::random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query);
+        msg.add_query(Query::query(Name::root(), RecordType::SOA));
+        let resp = self.outbound.query(source, msg, self.timeout).await?;
+        let serial = resp
+            .answers()
+            .iter()
+            .find_map(|r| r.data().as_soa())
+            .map(|soa| soa.serial())
+            .context("la fuente no devolvió SOA")?;
+
+        if let Some(cur) = self.zone() {
+            if !serial_newer(serial, cur.serial) {
+                // Misma copia: sigue vigente otro expire.
+                if let Some(l) = self.current.write().unwrap().as_mut() {
+                    l.expires = Instant::now() + l.zone.expire;
+                }
+                return Ok(());
+            }
+        }
+
+        let records = exchange::axfr(source, Name::root(), AXFR_TIMEOUT).await?;
+        let zone = RootZone::from_records(records)?;
+        self.check(&zone)?;
+        let expire = zone.expire;
+        self.install(zone, expire);
+        Ok(())
+    }
+
+    /// Respuesta desde la copia o, si no hay copia vigente, desde un root real.
+    async fn answer(&self, query: &Query, dnssec_ok: bool) -> Option<Reply> {
+        if query.query_class() != DNSClass::IN {
+            return Some(Reply::new(ResponseCode::Refused, false));
+        }
+        match self.zone() {
+            Some(zone) => Some(zone.lookup(query.name(), query.query_type(), dnssec_ok)),
+            None => self.relay(query, dnssec_ok).await,
+        }
+    }
+
+    async fn relay(&self, query: &Query, dnssec_ok: bool) -> Option<Reply> {
+        let mut msg = Message::new();
+        msg.set_id(rand::random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query);
+        msg.add_query(query.clone());
+        let mut edns = Edns::new();
+        edns.set_max_payload(EDNS_MAX_PAYLOAD);
+        edns.set_dnssec_ok(dnssec_ok);
+        msg.set_edns(edns);
+
+        for &root in &self.roots {
+            match self.outbound.query(root, msg.clone(), self.timeout).await {
+                Ok(mut resp) => {
+                    return Some(Reply {
+                        rcode: resp.response_code(),
+                        authoritative: resp.authoritative(),
+                        answers: resp.take_answers(),
+                        authority: resp.take_name_servers(),
+                        additionals: resp.take_additionals(),
+                    })
+                }
+                Err(e) => tracing::debug!("root mirror: reenvío a {root}: {e:#}"),
+            }
+        }
+        None
+    }
+}
+
+struct MirrorHandler(Arc<RootMirror>);
+
+#[async_trait::async_trait]
+impl RequestHandler for MirrorHandler {
+    async fn handle_request<R: ResponseHandler>(&self, req: &Request, mut response: R) -> ResponseInfo {
+        let dnssec_ok = req.edns().is_some_and(|e| e.flags().dnssec_ok);
+        let answered = match req.queries().first() {
+            Some(q) => self.0.answer(q.original(), dnssec_ok).await,
+            None => None,
+        };
+        let Some(reply) = answered else {
+            let msg = MessageResponseBuilder::from_message_request(req).error_msg(req.header(), ResponseCode::ServFail);
+            return response
+                .send_response(msg)
+                .await
+                .unwrap_or_else(|_| ResponseInfo::from(*req.header()));
+        };
+
+        let mut header = Header::response_from_request(req.header());
+        header.set_response_code(reply.rcode);
+        header.set_authoritative(reply.authoritative);
+        let mut builder = MessageResponseBuilder::from_message_request(req);
+        if let Some(req_edns) = req.edns() {
+            let mut edns = Edns::new();
+            edns.set_max_payload(req_edns.max_payload().clamp(512, EDNS_MAX_PAYLOAD));
+            edns.set_dnssec_ok(dnssec_ok);
+            builder.edns(edns);
+        }
+        let msg = builder.build(
+            header,
+            reply.answers.iter(),
+            reply.authority.iter(),
+            iter::empty(),
+            reply.additionals.iter(),
+        );
+        response
+            .send_response(msg)
+            .await
+            .unwrap_or_else(|_| ResponseInfo::from(*req.header()))
+    }
+}
@@ -0,0 +1,160 @@
+//! Root hints del recursor.
+//!
+//! Cada entrada de `roots` puede ser una IP (puerto 53), un `ip:port` (roots
+//! de laboratorio) o la ruta a un archivo `named.root` como el que baja
+//! `recursor-bootstrap fetch-roots`; del archivo se toman los A/AAAA.
+//!
+//! Los hints sólo sirven para el priming (RFC 8109): `. NS` a alguno de ellos
+//! y el set de roots sale de la respuesta.
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use hickory_proto::op::{Edns, Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{Name, RData, RecordType};
+
+use crate::outbound::Outbound;
+
+pub fn load(entries: &[String]) -> anyhow::Result<Vec<SocketAddr>> {
+    let mut out = Vec::new();
+    for e in entries {
+        let e = e.trim();
+        if let Ok(sa) = e.parse::<SocketAddr>() {
+            push_unique(&mut out, sa);
+        } else if let Ok(ip) = e.parse::<IpAddr>() {
+            push_unique(&mut out, SocketAddr::new(ip, 53));
+        } else {
+            let path = Path::new(e);
+            let txt = std::fs::read_to_string(path)
+                .with_context(|| format!("roots: {e} no es IP, ip:port ni un archivo legible"))?;
+            let ips = parse_hints(&txt);
+            if ips.is_empty() {
+                anyhow::bail!("roots: no encontré registros A/AAAA en {}", path.display());
+            }
+            for ip in ips {
+                push_unique(&mut out, SocketAddr::new(ip, 53));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn push_unique(out: &mut Vec<SocketAddr>, sa: SocketAddr) {
+    if !out.contains(&sa) {
+        out.push(sa);
+    }
+}
+
+/// A/AAAA de un archivo de hints en formato de zona:
+/// `A.ROOT-SERVERS.NET. 3600000 [IN] A 198.41.0.4`.
+pub fn parse_hints(txt: &str) -> Vec<IpAddr> {
+    let mut ips = Vec::new();
+    for line in txt.lines() {
+        let line = line.split(';').next().unwrap_or("").trim();
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let Some(pos) = parts
+            .iter()
+            .skip(1)
+            .position(|p| p.eq_ignore_ascii_case("A") || p.eq_ignore_ascii_case("AAAA"))
+        else {
+            continue;
+        };
+        if let Some(ip) = parts.get(pos + 2).and_then(|v| v.parse::<IpAddr>().ok()) {
+            if !ips.contains(&ip) {
+                ips.push(ip);
+            }
+        }
+    }
+    ips
+}
+
+/// Resultado del priming: direcciones de los roots (glue de la respuesta) y
+/// TTL del RRset `. NS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Primed {
+    pub addrs: Vec<SocketAddr>,
+    pub ttl: u32,
+}
+
+/// `. NS` a cada candidato en orden (UDP, TCP si viene truncada) hasta que
+/// uno responda bien.
+pub async fn prime(candidates: &[SocketAddr], outbound: &Outbound, timeout: Duration) -> anyhow::Result<Primed> {
+    let mut msg = Message::new();
+    msg.set_id(rand::random())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(false);
+    msg.add_query(Query::query(Name::root(), RecordType::NS));
+    let mut edns = Edns::new();
+    edns.set_max_payload(1232);
+    msg.set_edns(edns);
+
+    let mut last_err = anyhow::anyhow!("sin roots para el priming");
+    for &addr in candidates {
+        let resp = outbound.query(addr, msg.clone(), timeout).await;
+        let primed = resp.and_then(|r| primed_from(&r)).and_then(|mut p| {
+            outbound.families.select(&mut p.addrs);
+            if p.addrs.is_empty() {
+                anyhow::bail!("ningún root de una familia habilitada");
+            }
+            Ok(p)
+        });
+        match primed {
+            Ok(p) => return Ok(p),
+            Err(e) => last_err = e.context(format!("priming contra {addr}")),
+        }
+    }
+    Err(last_err)
+}
+
+/// Direcciones de los NS de la raíz según la respuesta de priming.
+pub fn primed_from(resp: &Message) -> anyhow::Result<Primed> {
+    if resp.response_code() != ResponseCode::NoError {
+        anyhow::bail!("rcode {}", resp.response_code());
+    }
+    let ns: Vec<(&Name, u32)> = resp
+        .answers()
+        .iter()
+        .filter(|r| r.name().is_root())
+        .filter_map(|r| match r.data() {
+            RData::NS(ns) => Some((&ns.0, r.ttl())),
+            _ => None,
+        })
+        .collect();
+    if ns.is_empty() {
+        anyhow::bail!("la respuesta no trae `. NS`");
+    }
+    let mut addrs = Vec::new();
+    for r in resp.additionals() {
+        if !ns.iter().any(|(name, _)| *name == r.name()) {
+            continue;
+        }
+        let ip = match r.data() {
+            RData::A(a) => IpAddr::V4(a.0),
+            RData::AAAA(aaaa) => IpAddr::V6(aaaa.0),
+            _ => continue,
+        };
+        push_unique(&mut addrs, SocketAddr::new(ip, 53));
+    }
+    if addrs.is_empty() {
+        anyhow::bail!("la respuesta no trae glue de los roots");
+    }
+    let ttl = ns.iter().map(|(_, ttl)| *ttl).min().unwrap_or_default();
+    Ok(Primed { addrs, ttl })
+}
+
+/// Diferencias entre los hints y lo que reportan los roots, para loguear.
+pub fn divergence(hints: &[SocketAddr], primed: &[SocketAddr]) -> Option<String> {
+    let missing: Vec<String> = primed.iter().filter(|a| !hints.contains(a)).map(|a| a.ip().to_string()).collect();
+    let extra: Vec<String> = hints.iter().filter(|a| !primed.contains(a)).map(|a| a.ip().to_string()).collect();
+    if missing.is_empty() && extra.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "faltan en los hints [{}], sobran [{}]",
+        missing.join(", "),
+        extra.join(", ")
+    ))
+}
@@ -0,0 +1,271 @@
+//! Cache de respuestas positivas por RRset: cada (dueño, tipo) vive con su
+//! propio TTL y nivel de confianza, y las respuestas se arman siguiendo la
+//! cadena CNAME dentro del cache. Así el destino de un CNAME sirve también a
+//! quien lo pregunta directo, y un RRset de TTL corto no arrastra al resto.
+//!
+//! Sólo se guardan los RRsets alcanzables desde la pregunta (el RRset pedido
+//! o la cadena CNAME hasta él); lo demás que traiga la answer se descarta.
+//! Las RRSIG van con el RRset que cubren. ANY no se guarda.
+
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use moka::future::Cache;
+
+use crate::cache::CacheKey;
+use crate::config::CacheConfig;
+
+/// TTL con el que se sirve un RRset vencido (RFC 8767 §4).
+const STALE_TTL: u32 = 30;
+
+/// Confianza de un RRset: sólo se reemplaza uno vigente por otro de igual o
+/// mayor confianza (RFC 2181 §5.4.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Trust {
+    Insecure,
+    /// Validado (AD del upstream o prueba DNSSEC del recursor).
+    Secure,
+}
+
+impl Trust {
+    pub fn of(secure: bool) -> Self {
+        if secure {
+            Self::Secure
+        } else {
+            Self::Insecure
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct RrsetKey {
+    owner: String,
+    rtype: u16,
+    subnet: Option<(IpAddr, u8)>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedRrset {
+    /// El RRset y sus RRSIG.
+    records: Vec<Record>,
+    trust: Trust,
+    /// Guardado desde una respuesta con DO=1: trae las RRSIG si la zona está
+    /// firmada. Sólo estos sirven a clientes con DO.
+    dnssec_ok: bool,
+    expires_at: Instant,
+    stale_until: Instant,
+}
+
+impl CachedRrset {
+    fn rank(&self) -> (Trust, bool) {
+        (self.trust, self.dnssec_ok)
+    }
+}
+
+/// Respuesta armada desde el cache: la cadena de RRsets con el TTL que les
+/// queda; vence y se vuelve stale con el primero de ellos.
+#[derive(Debug, Clone)]
+pub struct Assembled {
+    pub answers: Vec<Record>,
+    pub trust: Trust,
+    pub expires_at: Instant,
+    pub stale_until: Instant,
+}
+
+#[derive(Clone)]
+pub struct RrsetCache {
+    cache: Cache<RrsetKey, CachedRrset>,
+    /// Largo máximo de una cadena CNAME armada desde el cache.
+    max_chain: usize,
+    min_ttl: Duration,
+    max_ttl: Duration,
+    stale_window: Duration,
+}
+
+impl RrsetCache {
+    pub fn new(cfg: &CacheConfig, max_chain: usize) -> Self {
+        Self {
+            cache: Cache::builder().max_capacity(cfg.answer_cache_size).build(),
+            max_chain,
+            min_ttl: Duration::from_secs(cfg.min_ttl),
+            max_ttl: Duration::from_secs(cfg.max_ttl),
+            stale_window: Duration::from_secs(cfg.stale_window_secs),
+        }
+    }
+
+    /// Guarda los RRsets de una answer positiva para `key`. `false` si la
+    /// cadena no llega al tipo pedido (CNAME sin destino: es un NODATA).
+    pub async fn insert_answer(&self, key: &CacheKey, answers: &[Record], trust: Trust) -> bool {
+        let qtype = RecordType::from(key.qtype);
+        if qtype == RecordType::ANY {
+            return false;
+        }
+        let now = Instant::now();
+        let mut owner = key.qname_lc.clone();
+        for _ in 0..=self.max_chain {
+            if self.put(key, &owner, qtype, answers, trust, now).await {
+                return true;
+            }
+            if qtype == RecordType::CNAME || !self.put(key, &owner, RecordType::CNAME, answers, trust, now).await {
+                return false;
+            }
+            let Some(target) = answers.iter().find_map(|r| match r.data() {
+                RData::CNAME(c) if owner_key(r.name()) == owner => Some(&c.0),
+                _ => None,
+            }) else {
+                return false;
+            };
+            owner = owner_key(target);
+        }
+        false
+    }
+
+    /// Arma la respuesta para `key` desde el cache, siguiendo CNAMEs. `None`
+    /// si falta algún RRset de la cadena o ya pasó su ventana stale.
+    pub async fn lookup(&self, key: &CacheKey) -> Option<Assembled> {
+        let qtype = RecordType::from(key.qtype);
+        let now = Instant::now();
+        let mut chain = Vec::new();
+        let mut owner = key.qname_lc.clone();
+        for _ in 0..=self.max_chain {
+            if let Some(set) = self.get(key, &owner, qtype, now).await {
+                chain.push(set);
+                return Some(Assembled::new(chain, key.do_bit, now));
+            }
+            if qtype == RecordType::CNAME {
+                return None;
+            }
+            let cname = self.get(key, &owner, RecordType::CNAME, now).await?;
+            owner = cname.records.iter().find_map(|r| match r.data() {
+                RData::CNAME(c) => Some(owner_key(&c.0)),
+                _ => None,
+            })?;
+            chain.push(cname);
+        }
+        None
+    }
+
+    pub fn invalidate_all(&self) {
+        self.cache.invalidate_all();
+    }
+
+    pub fn entry_count(&self) -> u64 {
+        self.cache.entry_count()
+    }
+
+    async fn get(&self, key: &CacheKey, owner: &str, rtype: RecordType, now: Instant) -> Option<CachedRrset> {
+        self.cache
+            .get(&rrset_key(key, owner, rtype))
+            .await
+            .filter(|e| now < e.stale_until && (e.dnssec_ok || !key.do_bit))
+    }
+
+    /// Guarda el RRset `(owner, rtype)` de `answers`, si está; un RRset
+    /// vigente de más confianza se deja como está.
+    async fn put(
+        &self,
+        key: &CacheKey,
+        owner: &str,
+        rtype: RecordType,
+        answers: &[Record],
+        trust: Trust,
+        now: Instant,
+    ) -> bool {
+        let at_owner = || answers.iter().filter(|r| owner_key(r.name()) == owner);
+        let mut records: Vec<Record> = at_owner().filter(|r| r.record_type() == rtype).cloned().collect();
+        if records.is_empty() {
+            return false;
+        }
+        if rtype != RecordType::RRSIG {
+            records.extend(at_owner().filter(|r| covered(r) == Some(rtype)).cloned());
+        }
+
+        let ttl = records.iter().map(|r| r.ttl()).min().unwrap_or(0);
+        let ttl = Duration::from_secs(ttl.into()).clamp(self.min_ttl, self.max_ttl);
+        let entry = CachedRrset {
+            records,
+            trust,
+            dnssec_ok: key.do_bit,
+            expires_at: now + ttl,
+            stale_until: now + ttl + self.stale_window,
+        };
+
+        let k = rrset_key(key, owner, rtype);
+        if let Some(old) = self.cache.get(&k).await {
+            if now < old.expires_at && old.rank() > entry.rank() {
+                return true;
+            }
+        }
+        self.cache.insert(k, entry).await;
+        true
+    }
+}
+
+impl Assembled {
+    fn new(chain: Vec<CachedRrset>, dnssec_ok: bool, now: Instant) -> Self {
+        let trust = chain.iter().map(|s| s.trust).min().unwrap_or(Trust::Insecure);
+        let expires_at = chain.iter().map(|s| s.expires_at).min().unwrap_or(now);
+        let stale_until = chain.iter().map(|s| s.stale_until).min().unwrap_or(now);
+
+        let mut answers = Vec::new();
+        for set in chain {
+            let ttl = if now < set.expires_at {
+                set.expires_at.duration_since(now).as_secs().max(1) as u32
+            } else {
+                STALE_TTL
+            };
+            let rtype = set.records.first().map(Record::record_type);
+            for mut r in set.records {
+                if !dnssec_ok && covered(&r).is_some() && rtype != Some(RecordType::RRSIG) {
+                    continue;
+                }
+                r.set_ttl(ttl);
+                answers.push(r);
+            }
+        }
+        Self {
+            answers,
+            trust,
+            expires_at,
+            stale_until,
+        }
+    }
+}
+
+fn rrset_key(key: &CacheKey, owner: &str, rtype: RecordType) -> RrsetKey {
+    RrsetKey {
+        owner: owner.to_string(),
+        rtype: rtype.into(),
+        subnet: key.subnet,
+    }
+}
+
+/// Dueño normalizado igual que `CacheKey::qname_lc`.
+fn owner_key(name: &Name) -> String {
+    name.to_ascii().trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Tipo que cubre una RRSIG (`None` si `r` no es una RRSIG).
+#[cfg(feature = "dnssec")]
+fn covered(r: &Record) -> Option<RecordType> {
+    use hickory_proto::dnssec::rdata::DNSSECRData;
+
+    match r.data() {
+        RData::DNSSEC(DNSSECRData::RRSIG(sig)) => Some(sig.type_covered()),
+        _ => None,
+    }
+}
+
+/// Sin dnssec las RRSIG llegan como RData desconocida: el tipo cubierto son
+/// los dos primeros bytes (RFC 4034 §3.1).
+#[cfg(not(feature = "dnssec"))]
+fn covered(r: &Record) -> Option<RecordType> {
+    match r.data() {
+        RData::Unknown { code: RecordType::RRSIG, rdata } => match rdata.anything() {
+            [hi, lo, ..] => Some(RecordType::from(u16::from_be_bytes([*hi, *lo]))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,250 @@
+//! Trust anchors DNSSEC desde archivo (`recursor.trust_anchor_file`).
+//!
+//! Formato de zona, una entrada por registro (se aceptan paréntesis para
+//! partir líneas y comentarios con `;`):
+//!
+//! ```text
+//! .            172800 IN DNSKEY 257 3 8 AwEAAaz/tAm8yTn4Mfeh...
+//! .                   IN DS     20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D084...
+//! corp.example.       IN DNSKEY 257 3 13 mdsswUyr3DPW132mOi8V...
+//! ```
+//!
+//! Los DNSKEY se usan tal cual. Los DS se resuelven al arrancar: se pide el
+//! DNSKEY RRset del dueño, se busca la clave que cubre el DS y se verifica con
+//! ella la firma del RRset. hickory guarda sólo claves (sin dueño), así que un
+//! anchor de zona privada tiene que cubrir todo su DNSKEY RRset: con DS eso se
+//! hace solo; con DNSKEY hay que listar todas las claves de la zona.
+
+use std::path::Path;
+
+use anyhow::Context;
+use hickory_proto::dnssec::rdata::{DNSSECRData, DNSKEY, DS};
+use hickory_proto::dnssec::Verifier;
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_proto::serialize::txt::trust_anchor::{self, Entry};
+use hickory_proto::serialize::txt::RDataParser;
+
+/// Anchors leídos del archivo, todavía sin resolver los DS.
+#[derive(Debug, Clone, Default)]
+pub struct AnchorFile {
+    pub keys: Vec<(Name, DNSKEY)>,
+    pub ds: Vec<(Name, DS)>,
+}
+
+impl AnchorFile {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let txt = std::fs::read_to_string(path)
+            .with_context(|| format!("no pude leer trust anchors: {}", path.display()))?;
+        Self::parse(&txt).with_context(|| format!("trust anchors inválidos: {}", path.display()))
+    }
+
+    pub fn parse(txt: &str) -> anyhow::Result<Self> {
+        let mut out = Self::default();
+        for (line_no, entry) in entries(txt) {
+            out.parse_entry(&entry)
+                .with_context(|| format!("línea {line_no}: {entry}"))?;
+        }
+        if out.keys.is_empty() && out.ds.is_empty() {
+            anyhow::bail!("no hay ningún DNSKEY ni DS");
+        }
+        Ok(out)
+    }
+
+    fn parse_entry(&mut self, entry: &str) -> anyhow::Result<()> {
+        let tokens: Vec<&str> = entry.split_whitespace().collect();
+        let owner = *tokens.first().context("entrada vacía")?;
+        if !owner.ends_with('.') {
+            anyhow::bail!("el dueño tiene que ser absoluto (terminar en '.'): {owner}");
+        }
+        let owner = Name::from_ascii(owner).with_context(|| format!("nombre inválido: {owner}"))?;
+
+        // owner [ttl] [clase] tipo rdata...
+        let type_at = tokens
+            .iter()
+            .skip(1)
+            .take(3)
+            .position(|t| t.eq_ignore_ascii_case("DNSKEY") || t.eq_ignore_ascii_case("DS"))
+            .map(|i| i + 1)
+            .context("se esperaba un registro DNSKEY o DS")?;
+        let rdata = &tokens[type_at + 1..];
+
+        if tokens[type_at].eq_ignore_ascii_case("DS") {
+            let ds = match RData::parse(RecordType::DS, rdata.iter().copied(), None)? {
+                RData::DNSSEC(DNSSECRData::DS(ds)) => ds,
+                other => anyhow::bail!("se esperaba DS, vino {other:?}"),
+            };
+            self.ds.push((owner, ds));
+        } else {
+            let key = parse_dnskey(&owner, rdata)?;
+            if key.revoke() {
+                tracing::warn!("trust anchor {owner}: clave revocada, se ignora");
+                return Ok(());
+            }
+            self.keys.push((owner, key));
+        }
+        Ok(())
+    }
+
+    /// Dueños de los anchors DS (hay que traer su DNSKEY RRset).
+    pub fn ds_owners(&self) -> Vec<Name> {
+        let mut owners: Vec<Name> = Vec::new();
+        for (n, _) in &self.ds {
+            if !owners.contains(n) {
+                owners.push(n.clone());
+            }
+        }
+        owners
+    }
+
+    /// Todas las claves confiables con su dueño: los DNSKEY del archivo y las
+    /// que quedan validadas por cada DS. `dnskeys` tiene, por dueño de DS, la
+    /// respuesta (DNSKEY + RRSIG) a `<dueño> DNSKEY`.
+    pub fn resolve(self, dnskeys: &[(Name, Vec<Record>)]) -> anyhow::Result<Vec<(Name, DNSKEY)>> {
+        let owners = self.ds_owners();
+        let mut out = self.keys;
+        for owner in owners {
+            let ds: Vec<&DS> = self.ds.iter().filter(|(n, _)| *n == owner).map(|(_, d)| d).collect();
+            let rrset = dnskeys
+                .iter()
+                .find(|(n, _)| *n == owner)
+                .map(|(_, r)| r.as_slice())
+                .unwrap_or_default();
+            for key in keys_for_ds(&owner, &ds, rrset)? {
+                out.push((owner.clone(), key));
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Claves del DNSKEY RRset de `owner` que quedan confiables por `ds`: tiene
+/// que haber una clave cubierta por algún DS y una firma del RRset hecha con
+/// ella. Devuelve todas las claves (no revocadas) del RRset.
+pub fn keys_for_ds(owner: &Name, ds: &[&DS], records: &[Record]) -> anyhow::Result<Vec<DNSKEY>> {
+    let dnskeys: Vec<&Record> = records
+        .iter()
+        .filter(|r| r.record_type() == RecordType::DNSKEY && r.name() == owner)
+        .collect();
+    if dnskeys.is_empty() {
+        anyhow::bail!("trust anchor DS {owner}: no hay DNSKEY para ese nombre");
+    }
+
+    let covered: Vec<&DNSKEY> = dnskeys
+        .iter()
+        .filter_map(|r| dnskey_of(r))
+        .filter(|k| !k.revoke() && ds.iter().any(|d| d.covers(owner, k).unwrap_or(false)))
+        .collect();
+    if covered.is_empty() {
+        anyhow::bail!("trust anchor DS {owner}: ningún DNSKEY coincide con el DS");
+    }
+
+    if !dnskey_rrset_signed_by(owner, &covered, records, None) {
+        anyhow::bail!("trust anchor DS {owner}: el DNSKEY RRset no está firmado por la clave del DS");
+    }
+
+    Ok(dnskeys
+        .iter()
+        .filter_map(|r| dnskey_of(r))
+        .filter(|k| !k.revoke())
+        .cloned()
+        .collect())
+}
+
+/// DNSKEY en formato de presentación (`flags 3 alg base64...`). A diferencia
+/// de `AnchorFile` no descarta las claves revocadas.
+pub fn parse_dnskey(owner: &Name, rdata: &[&str]) -> anyhow::Result<DNSKEY> {
+    // El parser de hickory exige la clase; la TTL no nos importa.
+    let line = format!("{owner} IN DNSKEY {}", rdata.join(" "));
+    let parsed = trust_anchor::Parser::new(line.as_str()).parse()?;
+    let Some(Entry::DNSKEY(record)) = parsed.into_iter().next() else {
+        anyhow::bail!("se esperaba DNSKEY");
+    };
+    let key = record.data().clone();
+    if !key.zone_key() {
+        anyhow::bail!("el DNSKEY no tiene el flag de zona (256/257)");
+    }
+    Ok(key)
+}
+
+/// ¿Alguna RRSIG del DNSKEY RRset de `owner` en `records` la hizo una de
+/// `keys`? Con `now` (segundos unix) además se exige que la firma esté vigente.
+pub fn dnskey_rrset_signed_by(owner: &Name, keys: &[&DNSKEY], records: &[Record], now: Option<u64>) -> bool {
+    rrset_signed_by(owner, RecordType::DNSKEY, keys, records, now)
+}
+
+/// Igual que `dnskey_rrset_signed_by` para el RRset `rtype` de `owner`.
+pub fn rrset_signed_by(
+    owner: &Name,
+    rtype: RecordType,
+    keys: &[&DNSKEY],
+    records: &[Record],
+    now: Option<u64>,
+) -> bool {
+    let rrset: Vec<&Record> = records
+        .iter()
+        .filter(|r| r.record_type() == rtype && r.name() == owner)
+        .collect();
+    records
+        .iter()
+        .filter_map(|r| match r.data() {
+            RData::DNSSEC(DNSSECRData::RRSIG(sig)) if sig.type_covered() == rtype => Some(sig),
+            _ => None,
+        })
+        .filter(|sig| match now {
+            // RFC 4034 §3.1.5: segundos unix módulo 2^32.
+            Some(now) => {
+                let now = now as u32;
+                sig.sig_inception().get() <= now && now <= sig.sig_expiration().get()
+            }
+            None => true,
+        })
+        .any(|sig| {
+            keys.iter().any(|k| {
+                k.calculate_key_tag().is_ok_and(|tag| tag == sig.key_tag())
+                    && k.verify_rrsig(owner, DNSClass::IN, sig, rrset.iter().copied()).is_ok()
+            })
+        })
+}
+
+pub fn dnskey_of(r: &Record) -> Option<&DNSKEY> {
+    match r.data() {
+        RData::DNSSEC(DNSSECRData::DNSKEY(k)) => Some(k),
+        _ => None,
+    }
+}
+
+/// Entradas lógicas del archivo: sin comentarios y con los paréntesis
+/// unidos en una sola línea. Devuelve (número de línea inicial, entrada).
+fn entries(txt: &str) -> Vec<(usize, String)> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+    let mut depth = 0i32;
+
+    for (i, raw) in txt.lines().enumerate() {
+        let line = raw.split(';').next().unwrap_or("");
+        if current.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            start = i + 1;
+        }
+        for c in line.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => current.push(c),
+            }
+        }
+        current.push(' ');
+        if depth <= 0 {
+            out.push((start, current.split_whitespace().collect::<Vec<_>>().join(" ")));
+            current.clear();
+            depth = 0;
+        }
+    }
+    if !current.trim().is_empty() {
+        out.push((start, current.split_whitespace().collect::<Vec<_>>().join(" ")));
+    }
+    out
+}
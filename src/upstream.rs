@@ -0,0 +1,291 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hickory_resolver::TokioResolver;
+
+use crate::retry;
+
+/// Límites superiores (ms) de los buckets del histograma de latencia.
+/// El último bucket (implícito) es "> 2000ms".
+pub const LATENCY_BUCKETS_MS: [u64; 11] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000];
+
+/// Un upstream del forwarder: su resolver propio (UDP + TCP), sus contadores
+/// y su circuit breaker.
+pub struct Upstream {
+    pub addr: SocketAddr,
+    pub weight: u32,
+    pub resolver: TokioResolver,
+    pub stats: UpstreamStats,
+    pub breaker: Breaker,
+}
+
+/// Contadores por upstream. Todo atómico: se actualiza desde cada request.
+#[derive(Default)]
+pub struct UpstreamStats {
+    queries: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    /// Respuestas descartadas por la validación (ver `validation`).
+    rejected: AtomicU64,
+
+    /// SRTT suavizado en microsegundos (0 = todavía sin medir).
+    srtt_us: AtomicU64,
+
+    latency: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    Error,
+    Timeout,
+}
+
+impl UpstreamStats {
+    /// Registra una consulta terminada. Respuestas negativas (NXDOMAIN/NODATA)
+    /// cuentan como `Outcome::Ok`: el upstream respondió.
+    pub fn record(&self, outcome: Outcome, elapsed: Duration) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        match outcome {
+            Outcome::Ok => {}
+            Outcome::Error => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            Outcome::Timeout => {
+                self.timeouts.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let ms = elapsed.as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&limit| ms <= limit)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.latency[bucket].fetch_add(1, Ordering::Relaxed);
+
+        self.update_srtt(elapsed);
+    }
+
+    /// EWMA con alfa = 1/8 (como RFC 6298). Un timeout entra con el tiempo
+    /// esperado, lo que penaliza al upstream en la estrategia `lowest_srtt`.
+    fn update_srtt(&self, sample: Duration) {
+        let sample = (sample.as_micros() as u64).max(1);
+        let _ = self
+            .srtt_us
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old| {
+                Some(if old == 0 {
+                    sample
+                } else {
+                    old - old / 8 + sample / 8
+                })
+            });
+    }
+
+    pub fn record_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn srtt(&self) -> Option<Duration> {
+        match self.srtt_us.load(Ordering::Relaxed) {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    /// Cuantil `q` de la latencia según el histograma (cota superior del
+    /// bucket). `None` con menos de `min_samples` muestras o si cae en el
+    /// bucket abierto (> 2000ms).
+    pub fn latency_quantile(&self, q: f64, min_samples: u64) -> Option<Duration> {
+        let counts: Vec<u64> = self.latency.iter().map(|c| c.load(Ordering::Relaxed)).collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 || total < min_samples {
+            return None;
+        }
+
+        let target = ((total as f64) * q.clamp(0.0, 1.0)).ceil() as u64;
+        let mut acc = 0;
+        for (i, n) in counts.iter().enumerate() {
+            acc += n;
+            if acc >= target {
+                return LATENCY_BUCKETS_MS.get(i).map(|&ms| Duration::from_millis(ms));
+            }
+        }
+        None
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let mut latency = [0u64; LATENCY_BUCKETS_MS.len() + 1];
+        for (dst, src) in latency.iter_mut().zip(self.latency.iter()) {
+            *dst = src.load(Ordering::Relaxed);
+        }
+        StatsSnapshot {
+            queries: self.queries.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            srtt: self.srtt(),
+            latency,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StatsSnapshot {
+    pub queries: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    pub rejected: u64,
+    pub srtt: Option<Duration>,
+    pub latency: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+impl fmt::Display for StatsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "queries={} errors={} timeouts={} rejected={} srtt={}",
+            self.queries,
+            self.errors,
+            self.timeouts,
+            self.rejected,
+            self.srtt
+                .map(|d| format!("{:.1}ms", d.as_secs_f64() * 1000.0))
+                .unwrap_or_else(|| "-".to_string()),
+        )?;
+
+        write!(f, " latency=[")?;
+        for (i, n) in self.latency.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match LATENCY_BUCKETS_MS.get(i) {
+                Some(ms) => write!(f, "<={ms}ms:{n}")?,
+                None => write!(f, ">{}ms:{n}", LATENCY_BUCKETS_MS[i - 1])?,
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+/// Parámetros del circuit breaker (ver `[forwarder.health]`).
+#[derive(Debug, Clone)]
+pub struct BreakerPolicy {
+    /// Fallas consecutivas que disparan la expulsión.
+    pub failure_threshold: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Fracción de jitter aplicada al backoff (0.2 = ±20%).
+    pub jitter: f64,
+}
+
+/// Circuit breaker por upstream.
+///
+/// - cerrado: recibe tráfico; N fallas seguidas lo abren.
+/// - abierto: expulsado hasta `open_until` (backoff exponencial con jitter).
+/// - semi-abierto: vencido el backoff vuelve a recibir tráfico/probes; el
+///   primer éxito lo re-admite, la primera falla lo vuelve a abrir con más backoff.
+#[derive(Default)]
+pub struct Breaker {
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+    trips: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    Up,
+    Ejected { remaining: Duration },
+    HalfOpen,
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Up => write!(f, "up"),
+            Self::Ejected { remaining } => write!(f, "ejected ({}s)", remaining.as_secs()),
+            Self::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+impl Breaker {
+    pub fn status(&self) -> HealthStatus {
+        self.status_at(Instant::now())
+    }
+
+    /// Como `status`, con el reloj dado (tests).
+    pub fn status_at(&self, now: Instant) -> HealthStatus {
+        let st = self.state.lock().unwrap();
+        match st.open_until {
+            None => HealthStatus::Up,
+            Some(t) => {
+                if now < t {
+                    HealthStatus::Ejected { remaining: t - now }
+                } else {
+                    HealthStatus::HalfOpen
+                }
+            }
+        }
+    }
+
+    /// ¿Puede recibir tráfico? (cerrado o semi-abierto)
+    pub fn is_available(&self) -> bool {
+        !matches!(self.status(), HealthStatus::Ejected { .. })
+    }
+
+    /// Devuelve `true` si el upstream estaba expulsado y queda re-admitido.
+    pub fn on_success(&self) -> bool {
+        self.on_success_at(Instant::now())
+    }
+
+    pub fn on_success_at(&self, now: Instant) -> bool {
+        let mut st = self.state.lock().unwrap();
+        if st.open_until.is_some_and(|t| now < t) {
+            // Todavía en backoff: un éxito aislado no acorta la expulsión.
+            return false;
+        }
+        let readmitted = st.open_until.is_some();
+        *st = BreakerState::default();
+        readmitted
+    }
+
+    /// Devuelve la duración de la expulsión si esta falla abre el breaker.
+    pub fn on_failure(&self, policy: &BreakerPolicy) -> Option<Duration> {
+        self.on_failure_at(policy, Instant::now())
+    }
+
+    pub fn on_failure_at(&self, policy: &BreakerPolicy, now: Instant) -> Option<Duration> {
+        let mut st = self.state.lock().unwrap();
+        st.failures = st.failures.saturating_add(1);
+
+        let trip = match st.open_until {
+            Some(t) if now < t => false,
+            Some(_) => true,
+            None => st.failures >= policy.failure_threshold.max(1),
+        };
+        if !trip {
+            return None;
+        }
+
+        let backoff = backoff_with_jitter(policy, st.trips);
+        st.open_until = Some(now + backoff);
+        st.trips = st.trips.saturating_add(1);
+        Some(backoff)
+    }
+}
+
+fn backoff_with_jitter(policy: &BreakerPolicy, trips: u32) -> Duration {
+    let exp = policy
+        .backoff_base
+        .saturating_mul(1u32 << trips.min(16))
+        .min(policy.backoff_max);
+    retry::jittered(exp, policy.jitter)
+}
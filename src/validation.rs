@@ -0,0 +1,182 @@
+//! Validación de respuestas de upstream antes de que lleguen al cache.
+//!
+//! Modelo de amenaza: respuestas falsificadas en el camino hacia los
+//! upstreams. Se rechaza la respuesta entera (y el forwarder pasa al
+//! siguiente upstream) si:
+//! - la pregunta no coincide con la enviada (nombre byte a byte, para que
+//!   la capitalización 0x20 sirva de algo, tipo y clase);
+//! - hay registros fuera de bailiwick en answer/authority;
+//! - algún TTL es absurdo.
+
+use std::fmt;
+
+use hickory_proto::op::{Message, Query};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+
+/// TTL máximo válido (RFC 2181 §8: el bit alto en 1 no es un TTL).
+const RFC2181_MAX_TTL: u32 = i32::MAX as u32;
+
+/// DNAME (RFC 6672): hickory 0.25 no tiene variante propia.
+pub(crate) const DNAME: RecordType = RecordType::Unknown(39);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    QuestionMismatch { expected: String, got: String },
+    OutOfBailiwick { section: &'static str, owner: Name },
+    AbsurdTtl { owner: Name, ttl: u32 },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::QuestionMismatch { expected, got } => {
+                write!(f, "pregunta no coincide: esperaba {expected}, vino {got}")
+            }
+            Self::OutOfBailiwick { section, owner } => {
+                write!(f, "registro fuera de bailiwick en {section}: {owner}")
+            }
+            Self::AbsurdTtl { owner, ttl } => write!(f, "TTL absurdo {ttl} en {owner}"),
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Validator {
+    max_ttl: u32,
+}
+
+impl Validator {
+    pub fn new(max_ttl: u32) -> Self {
+        Self {
+            max_ttl: max_ttl.min(RFC2181_MAX_TTL),
+        }
+    }
+
+    /// Respuesta completa (camino pass-through).
+    pub fn check_message(&self, sent: &Query, resp: &Message) -> Result<(), Rejection> {
+        let question_ok = match resp.queries() {
+            [q] => {
+                q.name().eq_case(sent.name())
+                    && q.query_type() == sent.query_type()
+                    && q.query_class() == sent.query_class()
+            }
+            _ => false,
+        };
+        if !question_ok {
+            return Err(Rejection::QuestionMismatch {
+                expected: sent.to_string(),
+                got: resp
+                    .queries()
+                    .iter()
+                    .map(|q| q.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            });
+        }
+
+        let chain = answer_chain(sent.name(), resp.answers())?;
+        check_authority(&chain, resp.name_servers())?;
+
+        let all = resp
+            .answers()
+            .iter()
+            .chain(resp.name_servers())
+            .chain(resp.additionals());
+        self.check_ttls(all)
+    }
+
+    /// Registros sueltos de un `Lookup` (camino `TokioResolver`): hickory ya
+    /// valida la pregunta; acá sólo bailiwick de answers y TTLs.
+    pub fn check_records(&self, qname: &Name, records: &[Record]) -> Result<(), Rejection> {
+        answer_chain(qname, records)?;
+        self.check_ttls(records.iter())
+    }
+
+    fn check_ttls<'a>(&self, records: impl Iterator<Item = &'a Record>) -> Result<(), Rejection> {
+        for r in records {
+            if r.record_type() == RecordType::OPT {
+                continue;
+            }
+            if r.ttl() > self.max_ttl {
+                return Err(Rejection::AbsurdTtl {
+                    owner: r.name().clone(),
+                    ttl: r.ttl(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Nombres alcanzables desde `qname` siguiendo CNAME en `answers`; todo
+/// registro de answer debe pertenecer a alguno de ellos. Un DNAME (que hickory
+/// no decodifica) se acepta si es de un ancestro: el CNAME sintetizado que lo
+/// acompaña es el que extiende la cadena.
+fn answer_chain(qname: &Name, answers: &[Record]) -> Result<Vec<Name>, Rejection> {
+    let mut chain = vec![qname.clone()];
+
+    // Punto fijo: los CNAME pueden venir en cualquier orden.
+    loop {
+        let before = chain.len();
+        for r in answers {
+            let target = match r.data() {
+                RData::CNAME(c) if chain.contains(r.name()) => c.0.clone(),
+                _ => continue,
+            };
+            if !chain.contains(&target) {
+                chain.push(target);
+            }
+        }
+        if chain.len() == before {
+            break;
+        }
+    }
+
+    for r in answers {
+        let owner = r.name();
+        let ok = chain.contains(owner)
+            || (r.record_type() == DNAME && chain.iter().any(|n| owner.zone_of(n)))
+            || (r.record_type() == RecordType::RRSIG && chain.iter().any(|n| owner.zone_of(n)));
+        if !ok {
+            return Err(Rejection::OutOfBailiwick {
+                section: "answer",
+                owner: owner.clone(),
+            });
+        }
+    }
+
+    Ok(chain)
+}
+
+/// Authority: SOA/NS tienen que ser de un ancestro (o el mismo nombre) de
+/// algún nombre de la cadena; NSEC/NSEC3/RRSIG, de dentro de esas zonas.
+fn check_authority(chain: &[Name], authority: &[Record]) -> Result<(), Rejection> {
+    let is_ancestor = |owner: &Name| chain.iter().any(|n| owner.zone_of(n));
+
+    let zones: Vec<&Name> = authority
+        .iter()
+        .filter(|r| matches!(r.record_type(), RecordType::SOA | RecordType::NS))
+        .map(|r| r.name())
+        .filter(|owner| is_ancestor(owner))
+        .collect();
+
+    for r in authority {
+        let owner = r.name();
+        let ok = match r.record_type() {
+            RecordType::SOA | RecordType::NS | RecordType::DS => is_ancestor(owner),
+            RecordType::NSEC | RecordType::NSEC3 | RecordType::RRSIG => {
+                is_ancestor(owner) || zones.iter().any(|z| z.zone_of(owner))
+            }
+            _ => false,
+        };
+        if !ok {
+            return Err(Rejection::OutOfBailiwick {
+                section: "authority",
+                owner: owner.clone(),
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+// Límites por zona: tasa y resoluciones en vuelo.
+
+use hickory_proto::rr::Name;
+
+use rust_dns_recursor::authority::{Refused, ZoneLimits};
+use rust_dns_recursor::config::{AuthorityConfig, ZoneLimitConfig};
+
+fn name(s: &str) -> Name {
+    Name::from_ascii(s).unwrap()
+}
+
+#[test]
+fn rate_limits_configured_zones() {
+    let auth = ZoneLimits::from_config(&AuthorityConfig {
+        zones: vec![
+            ZoneLimitConfig {
+                zone: "slow.".to_string(),
+                qps: 2,
+                max_inflight: 0,
+            },
+            ZoneLimitConfig {
+                zone: "fast.slow.".to_string(),
+                qps: 0,
+                max_inflight: 1,
+            },
+        ],
+    })
+    .unwrap();
+
+    assert!(auth.zone(&name("www.example.com.")).unwrap().is_none());
+
+    assert!(auth.zone(&name("a.slow.")).unwrap().is_some());
+    assert!(auth.zone(&name("b.slow.")).unwrap().is_some());
+    assert_eq!(auth.zone(&name("c.slow.")).err(), Some(Refused::RateLimited));
+
+    // La zona más específica manda: sin tope de tasa, uno en vuelo.
+    let held = auth.zone(&name("a.fast.slow.")).unwrap();
+    assert!(held.is_some());
+    assert_eq!(auth.zone(&name("b.fast.slow.")).err(), Some(Refused::Busy));
+    drop(held);
+    assert!(auth.zone(&name("b.fast.slow.")).unwrap().is_some());
+    assert_eq!(auth.limited(), 2);
+}
+
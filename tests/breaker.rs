@@ -0,0 +1,96 @@
+// Circuit breaker de upstreams: tests deterministas, sin red ni sleeps (el
+// reloj se pasa explícito).
+
+use std::time::{Duration, Instant};
+
+use rust_dns_recursor::upstream::{Breaker, BreakerPolicy, HealthStatus};
+
+fn policy(base: Duration) -> BreakerPolicy {
+    BreakerPolicy {
+        failure_threshold: 3,
+        backoff_base: base,
+        backoff_max: Duration::from_secs(300),
+        jitter: 0.0,
+    }
+}
+
+#[test]
+fn trips_after_threshold_consecutive_failures() {
+    let b = Breaker::default();
+    let p = policy(Duration::from_secs(5));
+    let t0 = Instant::now();
+
+    assert_eq!(b.on_failure_at(&p, t0), None);
+    assert_eq!(b.on_failure_at(&p, t0), None);
+    assert_eq!(b.on_failure_at(&p, t0), Some(Duration::from_secs(5)));
+    assert_eq!(
+        b.status_at(t0 + Duration::from_secs(1)),
+        HealthStatus::Ejected {
+            remaining: Duration::from_secs(4)
+        }
+    );
+}
+
+#[test]
+fn success_resets_failure_count() {
+    let b = Breaker::default();
+    let p = policy(Duration::from_secs(5));
+    let t0 = Instant::now();
+
+    b.on_failure_at(&p, t0);
+    b.on_failure_at(&p, t0);
+    assert!(!b.on_success_at(t0));
+    assert_eq!(b.on_failure_at(&p, t0), None);
+    assert_eq!(b.status_at(t0), HealthStatus::Up);
+}
+
+#[test]
+fn success_during_backoff_does_not_readmit() {
+    let b = Breaker::default();
+    let p = policy(Duration::from_secs(5));
+    let t0 = Instant::now();
+
+    for _ in 0..3 {
+        b.on_failure_at(&p, t0);
+    }
+    assert!(!b.on_success_at(t0 + Duration::from_secs(1)));
+    assert!(matches!(b.status_at(t0 + Duration::from_secs(1)), HealthStatus::Ejected { .. }));
+}
+
+#[test]
+fn half_open_failure_doubles_backoff_and_success_readmits() {
+    let b = Breaker::default();
+    let p = policy(Duration::from_secs(5));
+    let t0 = Instant::now();
+
+    for _ in 0..3 {
+        b.on_failure_at(&p, t0);
+    }
+    let t1 = t0 + Duration::from_secs(6);
+    assert_eq!(b.status_at(t1), HealthStatus::HalfOpen);
+
+    // Falla en semi-abierto: vuelve a expulsar con el doble de backoff.
+    assert_eq!(b.on_failure_at(&p, t1), Some(Duration::from_secs(10)));
+    assert!(matches!(b.status_at(t1 + Duration::from_secs(9)), HealthStatus::Ejected { .. }));
+
+    let t2 = t1 + Duration::from_secs(11);
+    assert_eq!(b.status_at(t2), HealthStatus::HalfOpen);
+    assert!(b.on_success_at(t2));
+    assert_eq!(b.status_at(t2), HealthStatus::Up);
+}
+
+#[test]
+fn backoff_is_capped() {
+    let b = Breaker::default();
+    let mut p = policy(Duration::from_secs(5));
+    p.backoff_max = Duration::from_secs(12);
+    let mut now = Instant::now();
+
+    for _ in 0..3 {
+        b.on_failure_at(&p, now);
+    }
+    for expected in [10, 12, 12] {
+        now += Duration::from_secs(13);
+        assert_eq!(b.on_failure_at(&p, now), Some(Duration::from_secs(expected)));
+    }
+}
@@ -0,0 +1,65 @@
+// Cache del front: vaciado y contadores de `status.server.`.
+
+use std::time::Duration;
+
+use hickory_proto::rr::rdata::A;
+use hickory_proto::rr::{Name, RData, Record};
+use rust_dns_recursor::cache::{CacheKey, CacheStats, CachedEntry, DnsCaches};
+use rust_dns_recursor::config::{CacheConfig, NegativeCacheConfig};
+use rust_dns_recursor::rrset_cache::Trust;
+
+fn caches() -> DnsCaches {
+    DnsCaches::new(&CacheConfig {
+        answer_cache_size: 100,
+        negative_cache_size: 100,
+        min_ttl: 30,
+        max_ttl: 3600,
+        negative_ttl: 60,
+        prefetch_threshold_secs: 5,
+        stale_window_secs: 60,
+        negative: NegativeCacheConfig::default(),
+    }, 8)
+}
+
+fn key(name: &str) -> CacheKey {
+    CacheKey {
+        qname_lc: name.to_string(),
+        qtype: 1,
+        do_bit: false,
+        subnet: None,
+    }
+}
+
+#[tokio::test]
+async fn flush_empties_every_front_cache() {
+    let c = caches();
+    let a = Record::from_rdata(Name::from_ascii("example.com.").unwrap(), 300, RData::A(A::new(192, 0, 2, 1)));
+    assert!(c.rrsets.insert_answer(&key("example.com"), &[a], Trust::Insecure).await);
+    let entry = CachedEntry::new(vec![0; 12], Duration::from_secs(300));
+    c.negative.insert(key("nope.example.com"), entry).await;
+    c.negative_probe.insert(key("probe.example.com"), 1).await;
+
+    c.flush();
+    assert!(c.rrsets.lookup(&key("example.com")).await.is_none());
+    assert!(c.negative.get(&key("nope.example.com")).await.is_none());
+    assert!(c.negative_probe.get(&key("probe.example.com")).await.is_none());
+    assert!(c.status_line().contains("flushes=1"), "{}", c.status_line());
+}
+
+#[test]
+fn status_line_reports_counters_and_clamps() {
+    let c = caches();
+    CacheStats::count(&c.stats.hits);
+    CacheStats::count(&c.stats.hits);
+    CacheStats::count(&c.stats.misses);
+    let line = c.status_line();
+    assert!(line.contains("hits=2 "), "{line}");
+    assert!(line.contains("misses=1 "), "{line}");
+    assert!(line.contains("min_ttl=30s max_ttl=3600s"), "{line}");
+}
+
+#[test]
+fn negative_entries_expire_with_their_ttl() {
+    assert!(CachedEntry::new(vec![0; 12], Duration::from_secs(300)).is_live());
+    assert!(!CachedEntry::new(vec![0; 12], Duration::ZERO).is_live());
+}
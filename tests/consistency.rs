@@ -0,0 +1,75 @@
+// Chequeo de consistencia entre upstreams: tests deterministas, sin red.
+
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::rdata::A;
+use hickory_proto::rr::{Name, RData, Record};
+
+use rust_dns_recursor::config::ConsistencyConfig;
+use rust_dns_recursor::consistency::{fingerprint, Consistency, Verdict};
+
+fn a(ttl: u32, last: u8) -> Record {
+    let name = Name::from_str("www.bank.example.").unwrap();
+    Record::from_rdata(name, ttl, RData::A(A(Ipv4Addr::new(192, 0, 2, last))))
+}
+
+fn checker(policy: &str) -> Consistency {
+    let cfg = ConsistencyConfig {
+        suffixes: vec!["bank.example".to_string()],
+        policy: policy.to_string(),
+    };
+    Consistency::from_config(&cfg).unwrap().unwrap()
+}
+
+fn answers(fps: &[Vec<String>]) -> Vec<(String, Vec<String>)> {
+    fps.iter()
+        .enumerate()
+        .map(|(i, fp)| (format!("upstream{i}"), fp.clone()))
+        .collect()
+}
+
+#[test]
+fn applies_only_to_configured_suffixes() {
+    let c = checker("prefer_primary");
+    assert!(c.applies(&Name::from_str("www.bank.example.").unwrap()));
+    assert!(c.applies(&Name::from_str("BANK.example.").unwrap()));
+    assert!(!c.applies(&Name::from_str("notbank.example.").unwrap()));
+}
+
+#[test]
+fn fingerprint_ignores_ttl_and_order() {
+    let x = fingerprint(ResponseCode::NoError, [a(60, 1), a(60, 2)].iter());
+    let y = fingerprint(ResponseCode::NoError, [a(300, 2), a(10, 1)].iter());
+    assert_eq!(x, y);
+    assert_ne!(x, fingerprint(ResponseCode::NoError, [a(60, 1)].iter()));
+}
+
+#[test]
+fn policies_resolve_mismatches() {
+    let good = fingerprint(ResponseCode::NoError, [a(60, 1)].iter());
+    let evil = fingerprint(ResponseCode::NoError, [a(60, 66)].iter());
+
+    let same = answers(&[good.clone(), good.clone()]);
+    assert_eq!(checker("servfail").decide("q", &same), Verdict::Use(0));
+
+    let diff = answers(&[evil.clone(), good.clone()]);
+    assert_eq!(checker("prefer_primary").decide("q", &diff), Verdict::Use(0));
+    assert_eq!(checker("servfail").decide("q", &diff), Verdict::ServFail);
+
+    let three = answers(&[evil.clone(), good.clone(), good.clone()]);
+    assert_eq!(checker("majority").decide("q", &three), Verdict::Use(1));
+
+    let split = answers(&[evil, good]);
+    assert_eq!(checker("majority").decide("q", &split), Verdict::ServFail);
+}
+
+#[test]
+fn invalid_policy_is_rejected() {
+    let cfg = ConsistencyConfig {
+        suffixes: vec!["bank.example".to_string()],
+        policy: "coin_flip".to_string(),
+    };
+    assert!(Consistency::from_config(&cfg).is_err());
+}
@@ -9,7 +9,7 @@
 //   cargo test --test dns_integration -- --nocapture --ignored
 
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use tempfile::TempDir;
@@ -64,7 +64,6 @@ negative_ttl = 300
 # AppConfig requires a full [recursor] block even in forwarder mode
 [recursor]
 ns_cache_size = 4096
-record_cache_size = 32768
 recursion_limit = 32
 ns_recursion_limit = 16
 timeout_ms = 1500
@@ -130,7 +129,6 @@ negative_ttl = 300
 
 [recursor]
 ns_cache_size = 4096
-record_cache_size = 32768
 recursion_limit = 32
 ns_recursion_limit = 16
 timeout_ms = 1500
@@ -144,16 +142,16 @@ dnssec = "off"
 }
 
 async fn start_server_from_cfg(
-    cfg_path: &PathBuf,
+    cfg_path: &Path,
 ) -> anyhow::Result<((SocketAddr, SocketAddr), tokio::task::JoinHandle<anyhow::Result<()>>)> {
     let cfg = AppConfig::load(cfg_path.to_str().unwrap())?;
 
     let zones = zones::ZoneStore::load_dir(&cfg.zones.zones_dir)?;
     let filters = filters::Filters::from_config(&cfg.filters)?;
-    let caches = cache::DnsCaches::new(&cfg.cache);
+    let caches = cache::DnsCaches::new(&cfg.cache, cfg.recursor.max_cname_chain);
 
     let forwarder = if let Some(ups) = cfg.upstreams.clone() {
-        Some(forwarder::build_forwarder(&ups).await?)
+        Some(forwarder::build_forwarder(&ups, &cfg.forwarder, &cfg.resolution).await?)
     } else {
         None
     };
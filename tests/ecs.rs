@@ -0,0 +1,72 @@
+// EDNS Client Subnet: truncado y claves de cache, sin red.
+
+use std::net::IpAddr;
+
+use hickory_proto::rr::rdata::opt::ClientSubnet;
+
+use rust_dns_recursor::cache::CacheKey;
+use rust_dns_recursor::config::EcsConfig;
+use rust_dns_recursor::ecs::{self, Ecs};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn ecs(client_ecs: &str) -> Ecs {
+    let cfg = EcsConfig {
+        enabled: true,
+        domains: vec!["cdn.example".to_string()],
+        client_ecs: client_ecs.to_string(),
+        ..EcsConfig::default()
+    };
+    Ecs::from_config(&cfg).unwrap().unwrap()
+}
+
+#[test]
+fn truncates_to_configured_prefixes() {
+    assert_eq!(ecs::truncate(ip("203.0.113.199"), 24), ip("203.0.113.0"));
+    assert_eq!(ecs::truncate(ip("203.0.113.199"), 0), ip("0.0.0.0"));
+    assert_eq!(ecs::truncate(ip("2001:db8:aaaa:bbcc::1"), 56), ip("2001:db8:aaaa:bb00::"));
+
+    let e = ecs("strip");
+    let v6 = e.source_for(ip("2001:db8:aaaa:bbcc::1"), None).unwrap();
+    assert_eq!((v6.addr(), v6.source_prefix()), (ip("2001:db8:aaaa:bb00::"), 56));
+}
+
+#[test]
+fn client_ecs_strip_vs_honour() {
+    let client = ClientSubnet::new(ip("192.0.2.0"), 20, 0);
+
+    // strip: se ignora lo que mandó y se usa la IP de origen.
+    let s = ecs("strip").source_for(ip("198.51.100.9"), Some(&client)).unwrap();
+    assert_eq!((s.addr(), s.source_prefix()), (ip("198.51.100.0"), 24));
+
+    // honour: se respeta (ya es más corto que /24).
+    let h = ecs("honour").source_for(ip("198.51.100.9"), Some(&client)).unwrap();
+    assert_eq!((h.addr(), h.source_prefix()), (ip("192.0.0.0"), 20));
+
+    // SOURCE PREFIX 0: el cliente pide que no se mande ECS.
+    let opt_out = ClientSubnet::new(ip("0.0.0.0"), 0, 0);
+    assert!(ecs("honour").source_for(ip("198.51.100.9"), Some(&opt_out)).is_none());
+}
+
+#[test]
+fn cache_key_follows_returned_scope() {
+    let base = CacheKey {
+        qname_lc: "www.cdn.example".to_string(),
+        qtype: 1,
+        do_bit: false,
+        subnet: None,
+    };
+    let a = base.with_subnet(ip("198.51.100.0"), 24, 24);
+    let b = base.with_subnet(ip("198.51.101.0"), 24, 24);
+    assert_ne!(a, b);
+
+    // Scope más corto: las dos subredes comparten entrada.
+    assert_eq!(
+        base.with_subnet(ip("198.51.100.0"), 24, 16),
+        base.with_subnet(ip("198.51.101.0"), 24, 16)
+    );
+    // Scope 0: respuesta global.
+    assert_eq!(base.with_subnet(ip("198.51.100.0"), 24, 0), base);
+}
@@ -0,0 +1,69 @@
+// Validación de `[forwarder]` y su traducción a `ResolverOpts` (sin tráfico DNS).
+
+use std::time::Duration;
+
+use hickory_resolver::config::LookupIpStrategy;
+use rust_dns_recursor::{
+    config::{ForwarderConfig, ResolutionConfig},
+    forwarder,
+};
+
+fn upstreams() -> Vec<String> {
+    vec!["127.0.0.1:5399".to_string()]
+}
+
+#[test]
+fn resolver_opts_from_config() -> anyhow::Result<()> {
+    let cfg = ForwarderConfig {
+        timeout_ms: 800,
+        edns0: true,
+        ip_strategy: "ipv6_then_ipv4".to_string(),
+        cache_size: 0,
+        num_concurrent_reqs: 1,
+        case_randomization: true,
+        ..ForwarderConfig::default()
+    };
+    let opts = forwarder::resolver_opts(&cfg)?;
+    assert_eq!(opts.timeout, Duration::from_millis(800));
+    // Los reintentos son de `[resolution]`: hickory hace uno por upstream.
+    assert_eq!(opts.attempts, 1);
+    assert!(opts.edns0);
+    assert_eq!(opts.ip_strategy, LookupIpStrategy::Ipv6thenIpv4);
+    assert_eq!(opts.cache_size, 0);
+    assert_eq!(opts.num_concurrent_reqs, 1);
+    assert!(opts.case_randomization);
+    Ok(())
+}
+
+#[test]
+fn ip_strategy_names_and_aliases() -> anyhow::Result<()> {
+    for (name, expected) in [
+        ("ipv4_only", LookupIpStrategy::Ipv4Only),
+        ("ipv6", LookupIpStrategy::Ipv6Only),
+        ("both", LookupIpStrategy::Ipv4AndIpv6),
+        ("IPv4_then_IPv6", LookupIpStrategy::Ipv4thenIpv6),
+    ] {
+        let cfg = ForwarderConfig {
+            ip_strategy: name.to_string(),
+            ..ForwarderConfig::default()
+        };
+        assert_eq!(forwarder::resolver_opts(&cfg)?.ip_strategy, expected, "{name}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn builds_with_valid_config() -> anyhow::Result<()> {
+    forwarder::build_forwarder(&upstreams(), &ForwarderConfig::default(), &ResolutionConfig::default()).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_ip_strategy_is_rejected() {
+    let cfg = ForwarderConfig {
+        ip_strategy: "ipv5".to_string(),
+        ..ForwarderConfig::default()
+    };
+    let err = forwarder::build_forwarder(&upstreams(), &cfg, &ResolutionConfig::default()).await.err().unwrap();
+    assert!(err.to_string().contains("ip_strategy"), "{err}");
+}
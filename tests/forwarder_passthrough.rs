@@ -0,0 +1,124 @@
+// Forwarder pass-through contra un upstream falso en loopback (sin Internet).
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use hickory_proto::op::{Edns, Message, MessageType, Query, ResponseCode};
+use hickory_proto::rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption};
+use hickory_proto::rr::{Name, RecordType};
+use tokio::net::UdpSocket;
+
+use rust_dns_recursor::{
+    config::{EcsConfig, ForwarderConfig, ResolutionConfig},
+    forwarder,
+};
+
+/// Upstream que contesta a todo NXDOMAIN con AD=1 y una opción EDE (15);
+/// si la consulta trae ECS lo devuelve con SCOPE = SOURCE.
+async fn fake_upstream() -> anyhow::Result<String> {
+    let sock = UdpSocket::bind("127.0.0.1:0").await?;
+    let addr = sock.local_addr()?.to_string();
+
+    tokio::spawn(async move {
+        let mut buf = [0u8; 4096];
+        while let Ok((n, peer)) = sock.recv_from(&mut buf).await {
+            let Ok(q) = Message::from_vec(&buf[..n]) else { continue };
+            let mut r = Message::new();
+            r.set_id(q.id());
+            r.set_message_type(MessageType::Response);
+            r.set_recursion_desired(q.recursion_desired());
+            r.set_recursion_available(true);
+            r.set_authentic_data(true);
+            r.set_checking_disabled(q.checking_disabled());
+            r.set_response_code(ResponseCode::NXDomain);
+            r.add_queries(q.queries().iter().cloned());
+            if let Some(qe) = q.extensions() {
+                let mut edns = Edns::new();
+                edns.set_dnssec_ok(qe.flags().dnssec_ok);
+                edns.options_mut().insert(EdnsOption::Unknown(15, vec![0, 6]));
+                if let Some(EdnsOption::Subnet(sub)) = qe.option(EdnsCode::Subnet) {
+                    let mut echo = *sub;
+                    echo.set_scope_prefix(sub.source_prefix());
+                    edns.options_mut().insert(EdnsOption::Subnet(echo));
+                }
+                r.set_edns(edns);
+            }
+            let _ = sock.send_to(&r.to_vec().unwrap(), peer).await;
+        }
+    });
+
+    Ok(addr)
+}
+
+#[tokio::test]
+async fn passthrough_keeps_flags_and_edns_options() -> anyhow::Result<()> {
+    let upstream = fake_upstream().await?;
+    let cfg = ForwarderConfig {
+        passthrough: true,
+        timeout_ms: 1000,
+        ..ForwarderConfig::default()
+    };
+    let fwd = forwarder::build_forwarder(&[upstream], &cfg, &ResolutionConfig::default()).await?;
+    assert!(fwd.passthrough());
+
+    let mut q = Message::new();
+    q.set_recursion_desired(true);
+    q.set_checking_disabled(true);
+    q.add_query(Query::query(Name::from_str("nx.example.")?, RecordType::A));
+    let mut edns = Edns::new();
+    edns.set_dnssec_ok(true);
+    q.set_edns(edns);
+
+    let resp = fwd.forward(&q, None).await?;
+    assert_eq!(resp.response_code(), ResponseCode::NXDomain);
+    assert!(resp.authentic_data());
+    assert!(resp.checking_disabled());
+
+    let opts = resp.extensions().as_ref().expect("EDNS en la respuesta").options();
+    assert!(opts.get(EdnsCode::from(15)).is_some(), "falta EDE");
+    Ok(())
+}
+
+#[tokio::test]
+async fn ecs_is_added_truncated_and_echo_checked() -> anyhow::Result<()> {
+    let upstream = fake_upstream().await?;
+    let cfg = ForwarderConfig {
+        passthrough: true,
+        timeout_ms: 1000,
+        ecs: EcsConfig {
+            enabled: true,
+            ..EcsConfig::default()
+        },
+        ..ForwarderConfig::default()
+    };
+    let fwd = forwarder::build_forwarder(&[upstream], &cfg, &ResolutionConfig::default()).await?;
+
+    let client: IpAddr = "198.51.100.77".parse()?;
+    let src = fwd.ecs().unwrap().source_for(client, None).unwrap();
+    assert_eq!(src, ClientSubnet::new("198.51.100.0".parse()?, 24, 0));
+
+    let mut q = Message::new();
+    q.add_query(Query::query(Name::from_str("cdn.example.")?, RecordType::A));
+    q.set_edns(Edns::new());
+
+    let resp = fwd.forward(&q, Some(src)).await?;
+    let edns = resp.extensions().as_ref().expect("EDNS en la respuesta");
+    match edns.option(EdnsCode::Subnet) {
+        Some(EdnsOption::Subnet(got)) => assert_eq!(got.scope_prefix(), 24),
+        other => panic!("sin ECS en la respuesta: {other:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn ecs_requires_passthrough() {
+    let cfg = ForwarderConfig {
+        ecs: EcsConfig {
+            enabled: true,
+            ..EcsConfig::default()
+        },
+        ..ForwarderConfig::default()
+    };
+    let ups = vec!["127.0.0.1:5399".to_string()];
+    assert!(forwarder::build_forwarder(&ups, &cfg, &ResolutionConfig::default()).await.is_err());
+}
@@ -0,0 +1,108 @@
+// Request hedging: p95 por histograma y carrera real entre dos upstreams
+// falsos en loopback (uno lento, uno rápido).
+
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use hickory_proto::op::{Message, MessageType, Query, ResponseCode};
+use hickory_proto::rr::rdata::A;
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use tokio::net::UdpSocket;
+
+use rust_dns_recursor::config::{ForwarderConfig, ResolutionConfig};
+use rust_dns_recursor::forwarder;
+use rust_dns_recursor::upstream::{Outcome, UpstreamStats};
+
+#[test]
+fn latency_p95_uses_histogram_bucket_bounds() {
+    let stats = UpstreamStats::default();
+    assert_eq!(stats.latency_quantile(0.95, 20), None);
+
+    for _ in 0..19 {
+        stats.record(Outcome::Ok, Duration::from_millis(8));
+    }
+    // Menos muestras que el mínimo pedido.
+    assert_eq!(stats.latency_quantile(0.95, 20), None);
+
+    stats.record(Outcome::Ok, Duration::from_millis(150));
+    assert_eq!(stats.latency_quantile(0.95, 20), Some(Duration::from_millis(10)));
+    assert_eq!(stats.latency_quantile(1.0, 20), Some(Duration::from_millis(200)));
+}
+
+/// Upstream que contesta `A <ip>` tras `delay`.
+async fn fake_upstream(ip: Ipv4Addr, delay: Duration) -> anyhow::Result<String> {
+    let sock = UdpSocket::bind("127.0.0.1:0").await?;
+    let addr = sock.local_addr()?.to_string();
+
+    tokio::spawn(async move {
+        let mut buf = [0u8; 4096];
+        while let Ok((n, peer)) = sock.recv_from(&mut buf).await {
+            let Ok(q) = Message::from_vec(&buf[..n]) else { continue };
+            let mut r = Message::new();
+            r.set_id(q.id());
+            r.set_message_type(MessageType::Response);
+            r.set_recursion_available(true);
+            r.set_response_code(ResponseCode::NoError);
+            r.add_queries(q.queries().iter().cloned());
+            if let Some(query) = q.queries().first() {
+                r.add_answer(Record::from_rdata(query.name().clone(), 60, RData::A(A::from(ip))));
+            }
+            tokio::time::sleep(delay).await;
+            let _ = sock.send_to(&r.to_vec().unwrap(), peer).await;
+        }
+    });
+
+    Ok(addr)
+}
+
+#[tokio::test]
+async fn hedge_fires_after_delay_and_takes_first_answer() -> anyhow::Result<()> {
+    let slow = fake_upstream(Ipv4Addr::new(192, 0, 2, 1), Duration::from_secs(2)).await?;
+    let fast = fake_upstream(Ipv4Addr::new(192, 0, 2, 2), Duration::ZERO).await?;
+    let cfg = ForwarderConfig {
+        passthrough: true,
+        strategy: "ordered".to_string(),
+        timeout_ms: 5000,
+        hedge_delay_ms: 50,
+        ..ForwarderConfig::default()
+    };
+    let fwd = forwarder::build_forwarder(&[slow, fast], &cfg, &ResolutionConfig::default()).await?;
+
+    let mut q = Message::new();
+    q.set_recursion_desired(true);
+    q.add_query(Query::query(Name::from_str("hedge.example.")?, RecordType::A));
+
+    let started = Instant::now();
+    let resp = fwd.forward(&q, None).await?;
+    // Sin hedge la respuesta tardaría los 2s del upstream lento.
+    assert!(started.elapsed() < Duration::from_secs(1), "{:?}", started.elapsed());
+    assert_eq!(resp.answers()[0].data(), &RData::A(A::new(192, 0, 2, 2)));
+
+    let status = fwd.status_lines().join("\n");
+    assert!(status.contains("lanzados=1 gana_hedge=1 gana_original=0"), "{status}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_hedge_when_primary_answers_in_time() -> anyhow::Result<()> {
+    let fast = fake_upstream(Ipv4Addr::new(192, 0, 2, 1), Duration::ZERO).await?;
+    let other = fake_upstream(Ipv4Addr::new(192, 0, 2, 2), Duration::ZERO).await?;
+    let cfg = ForwarderConfig {
+        passthrough: true,
+        strategy: "ordered".to_string(),
+        timeout_ms: 5000,
+        hedge_delay_ms: 2000,
+        ..ForwarderConfig::default()
+    };
+    let fwd = forwarder::build_forwarder(&[fast, other], &cfg, &ResolutionConfig::default()).await?;
+
+    let mut q = Message::new();
+    q.add_query(Query::query(Name::from_str("hedge.example.")?, RecordType::A));
+    let resp = fwd.forward(&q, None).await?;
+    assert_eq!(resp.answers()[0].data(), &RData::A(A::new(192, 0, 2, 1)));
+
+    let status = fwd.status_lines().join("\n");
+    assert!(status.contains("lanzados=0"), "{status}");
+    Ok(())
+}
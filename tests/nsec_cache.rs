@@ -0,0 +1,134 @@
+// Síntesis de negativas desde NSEC/NSEC3 en cache (RFC 8198), sin red.
+// El cache no valida: las pruebas llegan ya validadas por el recursor.
+#![cfg(feature = "dnssec")]
+
+use hickory_proto::dnssec::rdata::{DNSSECRData, NSEC, NSEC3};
+use hickory_proto::dnssec::Nsec3HashAlgorithm;
+use hickory_proto::rr::rdata::SOA;
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+
+use rust_dns_recursor::nsec_cache::{base32hex, NsecCache};
+
+fn name(s: &str) -> Name {
+    Name::from_ascii(s).unwrap()
+}
+
+fn soa() -> Record {
+    let soa = SOA::new(name("ns.example."), name("admin.example."), 1, 3600, 600, 86400, 300);
+    Record::from_rdata(name("example."), 3600, RData::SOA(soa))
+}
+
+fn nsec(owner: &str, next: &str, types: &[RecordType]) -> Record {
+    let rdata = NSEC::new_cover_self(name(next), types.iter().copied());
+    Record::from_rdata(name(owner), 600, RData::DNSSEC(DNSSECRData::NSEC(rdata)))
+}
+
+fn nsec3_hash(n: &str) -> String {
+    let digest = Nsec3HashAlgorithm::SHA1.hash(b"ab", &name(n), 1).unwrap();
+    base32hex(digest.as_ref())
+}
+
+/// Cadena NSEC3 de un solo registro (el apex), que cubre cualquier otro hash.
+fn nsec3_apex(opt_out: bool) -> Record {
+    let digest = Nsec3HashAlgorithm::SHA1.hash(b"ab", &name("example."), 1).unwrap();
+    let rdata = NSEC3::new(
+        Nsec3HashAlgorithm::SHA1,
+        opt_out,
+        1,
+        b"ab".to_vec(),
+        digest.as_ref().to_vec(),
+        [RecordType::SOA, RecordType::NS, RecordType::A, RecordType::NSEC3PARAM],
+    );
+    let owner = name(&format!("{}.example.", nsec3_hash("example.")));
+    Record::from_rdata(owner, 600, RData::DNSSEC(DNSSECRData::NSEC3(rdata)))
+}
+
+fn nsec_cache() -> NsecCache {
+    let cache = NsecCache::new(100);
+    cache.insert(
+        &soa(),
+        &[
+            nsec("example.", "a.example.", &[RecordType::SOA, RecordType::NS]),
+            nsec("a.example.", "d.example.", &[RecordType::A]),
+        ],
+    );
+    cache
+}
+
+#[test]
+fn base32hex_matches_rfc4648() {
+    assert_eq!(base32hex(b""), "");
+    assert_eq!(base32hex(b"f"), "co");
+    assert_eq!(base32hex(b"foobar"), "cpnmuoj1e8");
+}
+
+#[test]
+fn nsec_range_and_wildcard_prove_nxdomain() {
+    let cache = nsec_cache();
+    let synth = cache.synthesize(&name("c.example."), RecordType::A).unwrap();
+    assert!(synth.nxdomain);
+    assert_eq!(synth.soa.name(), &name("example."));
+    assert_eq!(synth.proof.len(), 2);
+    // TTL: min(NSEC, SOA, MINIMUM).
+    assert!(synth.soa.ttl() <= 300 && synth.proof.iter().all(|r| r.ttl() <= 300));
+
+    // Fuera de los rangos conocidos no se sintetiza.
+    assert!(cache.synthesize(&name("z.example."), RecordType::A).is_none());
+    assert!(cache.synthesize(&name("c.other."), RecordType::A).is_none());
+}
+
+#[test]
+fn nsec_owner_without_type_proves_nodata() {
+    let cache = nsec_cache();
+    let synth = cache.synthesize(&name("a.example."), RecordType::AAAA).unwrap();
+    assert!(!synth.nxdomain);
+    assert!(cache.synthesize(&name("a.example."), RecordType::A).is_none());
+    // El DS del apex es de la zona padre.
+    assert!(cache.synthesize(&name("example."), RecordType::DS).is_none());
+}
+
+#[test]
+fn empty_non_terminal_is_nodata() {
+    let cache = NsecCache::new(100);
+    cache.insert(&soa(), &[nsec("a.example.", "x.b.example.", &[RecordType::A])]);
+    let synth = cache.synthesize(&name("b.example."), RecordType::A).unwrap();
+    assert!(!synth.nxdomain);
+}
+
+#[test]
+fn names_below_a_delegation_are_not_synthesized() {
+    let cache = NsecCache::new(100);
+    cache.insert(&soa(), &[nsec("sub.example.", "zz.example.", &[RecordType::NS])]);
+    assert!(cache.synthesize(&name("www.sub.example."), RecordType::A).is_none());
+    assert!(cache.synthesize(&name("sub.example."), RecordType::A).is_none());
+    assert!(!cache.synthesize(&name("sub.example."), RecordType::DS).unwrap().nxdomain);
+}
+
+#[test]
+fn nsec3_closest_encloser_proves_nxdomain() {
+    let cache = NsecCache::new(100);
+    cache.insert(&soa(), &[nsec3_apex(false)]);
+    let synth = cache.synthesize(&name("nope.example."), RecordType::A).unwrap();
+    assert!(synth.nxdomain);
+    assert_eq!(synth.proof.len(), 1);
+
+    let synth = cache.synthesize(&name("example."), RecordType::AAAA).unwrap();
+    assert!(!synth.nxdomain);
+    assert!(cache.synthesize(&name("example."), RecordType::A).is_none());
+}
+
+#[test]
+fn nsec3_opt_out_does_not_prove_nxdomain() {
+    let cache = NsecCache::new(100);
+    cache.insert(&soa(), &[nsec3_apex(true)]);
+    assert!(cache.synthesize(&name("nope.example."), RecordType::A).is_none());
+}
+
+#[test]
+fn cache_is_bounded() {
+    let cache = NsecCache::new(1);
+    cache.insert(&soa(), &[nsec("a.example.", "d.example.", &[RecordType::A])]);
+    cache.insert(&soa(), &[nsec("m.example.", "p.example.", &[RecordType::A])]);
+    assert!(cache.synthesize(&name("a.example."), RecordType::AAAA).is_some());
+    assert!(cache.synthesize(&name("m.example."), RecordType::AAAA).is_none());
+}
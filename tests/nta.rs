@@ -0,0 +1,63 @@
+// Negative trust anchors (RFC 7646): cobertura, vencimiento y estado.
+
+use std::time::Duration;
+
+use hickory_proto::rr::Name;
+
+use rust_dns_recursor::config::NtaConfig;
+use rust_dns_recursor::nta::{self, NegativeTrustAnchors};
+
+fn name(s: &str) -> Name {
+    Name::from_ascii(s).unwrap()
+}
+
+#[test]
+fn covers_domain_and_subdomains_only() -> anyhow::Result<()> {
+    let cfg = [NtaConfig {
+        domain: "Broken.Example.".to_string(),
+        lifetime_secs: 3600,
+    }];
+    let ntas = NegativeTrustAnchors::from_config(&cfg)?;
+
+    assert!(ntas.covers(&name("broken.example.")));
+    assert!(ntas.covers(&name("www.BROKEN.example.")));
+    assert!(!ntas.covers(&name("notbroken.example.")));
+    assert!(!ntas.covers(&name("example.")));
+
+    let status = ntas.status_lines();
+    assert_eq!(status.len(), 1);
+    assert!(status[0].starts_with("nta=broken.example vence_en="), "{status:?}");
+    Ok(())
+}
+
+#[test]
+fn runtime_add_renew_and_remove() {
+    let ntas = NegativeTrustAnchors::default();
+    let d = nta::parse_domain("corp.example").unwrap();
+
+    ntas.insert(d.clone(), Duration::from_secs(60));
+    ntas.insert(d.clone(), Duration::from_secs(600));
+    assert_eq!(ntas.status_lines().len(), 1);
+    assert!(ntas.covers(&name("a.corp.example.")));
+
+    assert!(ntas.remove(&name("CORP.example")));
+    assert!(!ntas.remove(&d));
+    assert!(!ntas.covers(&name("a.corp.example.")));
+}
+
+#[test]
+fn expired_anchors_stop_applying() {
+    let ntas = NegativeTrustAnchors::default();
+    ntas.insert(nta::parse_domain("gone.example").unwrap(), Duration::ZERO);
+    assert!(!ntas.covers(&name("gone.example.")));
+    assert!(ntas.status_lines().is_empty());
+}
+
+#[test]
+fn invalid_domain_is_rejected() {
+    let cfg = [NtaConfig {
+        domain: format!("{}.example", "x".repeat(64)),
+        lifetime_secs: 60,
+    }];
+    assert!(NegativeTrustAnchors::from_config(&cfg).is_err());
+}
@@ -0,0 +1,54 @@
+// Consultas salientes: familias y cadenas CNAME.
+
+use std::net::SocketAddr;
+
+use hickory_proto::rr::rdata::{A, CNAME};
+use hickory_proto::rr::{Name, RData, Record};
+
+use rust_dns_recursor::outbound::{cname_chain, Families};
+
+fn name(s: &str) -> Name {
+    Name::from_ascii(s).unwrap()
+}
+
+fn cname(from: &str, to: &str) -> Record {
+    Record::from_rdata(name(from), 300, RData::CNAME(CNAME(name(to))))
+}
+
+fn sa(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn filters_address_families() -> anyhow::Result<()> {
+    let roots = vec![sa("198.41.0.4:53"), sa("[2001:503:ba3e::2:30]:53"), sa("170.247.170.2:53")];
+
+    let mut addrs = roots.clone();
+    Families::new(true, true)?.select(&mut addrs);
+    assert_eq!(addrs, roots);
+    assert!(Families::new(true, true)?.deny_nets().is_empty());
+
+    let ipv6_only = Families::new(false, true)?;
+    let mut addrs = roots.clone();
+    ipv6_only.select(&mut addrs);
+    assert_eq!(addrs, [roots[1]]);
+    assert_eq!(ipv6_only.deny_nets().len(), 1);
+    assert!(ipv6_only.deny_nets()[0].contains(&"192.0.2.1".parse::<std::net::IpAddr>()?));
+
+    assert!(Families::new(false, false).is_err());
+    Ok(())
+}
+
+#[test]
+fn measures_cname_chains() {
+    let records = vec![
+        cname("www.example.com.", "a.example.net."),
+        cname("a.example.net.", "b.example.org."),
+        Record::from_rdata(name("b.example.org."), 300, RData::A(A::new(192, 0, 2, 1))),
+    ];
+    assert_eq!(cname_chain(&name("WWW.example.com."), &records), 2);
+    assert_eq!(cname_chain(&name("b.example.org."), &records), 0);
+
+    let looped = vec![cname("a.example.", "b.example."), cname("b.example.", "a.example.")];
+    assert_eq!(cname_chain(&name("a.example."), &looped), 2);
+}
@@ -0,0 +1,48 @@
+// Padding EDNS(0) (RFC 7830 / RFC 8467): tests deterministas, sin red.
+
+use std::str::FromStr;
+
+use hickory_proto::op::{Message, Query};
+use hickory_proto::rr::{Name, RecordType};
+use hickory_proto::xfer::Protocol;
+
+use rust_dns_recursor::{config::PaddingConfig, padding};
+
+fn query_message(name: &str) -> Message {
+    let mut m = Message::new();
+    m.set_id(4242);
+    m.set_recursion_desired(true);
+    m.add_query(Query::query(Name::from_str(name).unwrap(), RecordType::A));
+    m
+}
+
+#[test]
+fn pad_message_rounds_up_to_block() -> anyhow::Result<()> {
+    for (name, block) in [("example.com.", 128u16), ("a.very.long.name.example.org.", 468)] {
+        let mut m = query_message(name);
+        padding::pad_message(&mut m, block)?;
+        let len = m.to_vec()?.len();
+        assert_eq!(len % block as usize, 0, "len={len} block={block}");
+        assert!(padding::has_padding(m.extensions().as_ref().unwrap()));
+    }
+    Ok(())
+}
+
+#[test]
+fn pad_message_is_idempotent() -> anyhow::Result<()> {
+    let mut m = query_message("example.com.");
+    padding::pad_message(&mut m, 128)?;
+    let first = m.to_vec()?.len();
+    padding::pad_message(&mut m, 128)?;
+    assert_eq!(m.to_vec()?.len(), first);
+    Ok(())
+}
+
+#[test]
+fn plain_udp_is_never_padded() {
+    let p = padding::Padding::from_config(&PaddingConfig::default());
+    assert_eq!(p.response_block(Protocol::Udp), None);
+    assert_eq!(p.query_block(Protocol::Udp), None);
+    // TCP plano: apagado por defecto.
+    assert_eq!(p.response_block(Protocol::Tcp), None);
+}
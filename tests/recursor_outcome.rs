@@ -0,0 +1,100 @@
+// Clasificación de errores del recursor en NXDOMAIN/NODATA/SERVFAIL, sin red.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use hickory_proto::op::{Query, ResponseCode};
+use hickory_proto::rr::rdata::SOA;
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use hickory_proto::{ProtoError, ProtoErrorKind};
+use hickory_recursor::resolver::ResolveError;
+
+use rust_dns_recursor::recursor_engine::{Resolution, Security};
+
+fn soa() -> Record<SOA> {
+    let rdata = SOA::new(
+        Name::from_str("ns.example.").unwrap(),
+        Name::from_str("hm.example.").unwrap(),
+        1,
+        2,
+        3,
+        4,
+        60,
+    );
+    Record::from_rdata(Name::from_str("example.").unwrap(), 300, rdata)
+}
+
+fn no_records(rcode: ResponseCode) -> hickory_recursor::Error {
+    no_records_with(rcode, None)
+}
+
+fn no_records_with(rcode: ResponseCode, authorities: Option<Arc<[Record]>>) -> hickory_recursor::Error {
+    let query = Query::query(Name::from_str("nope.example.").unwrap(), RecordType::A);
+    let proto = ProtoError::nx_error(Box::new(query), Some(Box::new(soa())), None, None, rcode, true, authorities);
+    hickory_recursor::Error::from(ResolveError::from(proto))
+}
+
+#[test]
+fn nxdomain_keeps_soa() {
+    let res = Resolution::from_error(no_records(ResponseCode::NXDomain));
+    assert!(matches!(res, Resolution::NxDomain { soa: Some(_), .. }), "{res:?}");
+    assert_eq!(res.rcode(), ResponseCode::NXDomain);
+    assert!(!res.is_retryable());
+    assert!(matches!(res.authority().as_slice(), [r] if matches!(r.data(), RData::SOA(_))));
+}
+
+#[test]
+fn nodata_is_noerror_with_soa() {
+    let res = Resolution::from_error(no_records(ResponseCode::NoError));
+    assert!(matches!(res, Resolution::NoData { soa: Some(_), .. }), "{res:?}");
+    assert_eq!(res.rcode(), ResponseCode::NoError);
+    assert!(res.answers().is_empty());
+}
+
+#[test]
+fn failures_are_servfail_and_retryable() {
+    let timeout = hickory_recursor::Error::from(ResolveError::from(ProtoError::from(ProtoErrorKind::Timeout)));
+    let res = Resolution::from_error(timeout);
+    assert!(matches!(res, Resolution::Timeout));
+    assert_eq!(res.rcode(), ResponseCode::ServFail);
+    assert!(res.is_retryable());
+
+    let res = Resolution::from_error(hickory_recursor::Error::from("lame delegation"));
+    assert!(matches!(&res, Resolution::ServFail(why) if why.contains("lame")));
+    assert!(res.is_retryable());
+}
+
+#[test]
+fn negative_proofs_go_to_authority_and_strip_without_do() {
+    // Sólo importa el tipo: NSEC/RRSIG de la authority son la prueba.
+    let nsec = Record::update0(Name::from_str("example.").unwrap(), 60, RecordType::NSEC);
+    let ns = Record::update0(Name::from_str("example.").unwrap(), 60, RecordType::NS);
+    let mut res = Resolution::from_error(no_records_with(ResponseCode::NXDomain, Some(Arc::from(vec![nsec, ns]))));
+
+    let types: Vec<RecordType> = res.authority().iter().map(|r| r.record_type()).collect();
+    assert_eq!(types, [RecordType::SOA, RecordType::NSEC]);
+    // Sin pruebas validadas por hickory no hay AD.
+    assert_eq!(res.security(), Security::Insecure);
+
+    res.strip_dnssec();
+    assert!(matches!(res.authority().as_slice(), [r] if r.record_type() == RecordType::SOA));
+}
+
+#[cfg(feature = "dnssec")]
+#[test]
+fn unproven_negatives_are_bogus() {
+    use hickory_proto::dnssec::Proof;
+
+    let query = Query::query(Name::from_str("nope.example.").unwrap(), RecordType::A);
+    let nsec = |proof| {
+        let kind = ProtoErrorKind::Nsec { query: Box::new(query.clone()), proof };
+        hickory_recursor::Error::from(ResolveError::from(ProtoError::from(kind)))
+    };
+    let res = Resolution::from_error(nsec(Proof::Bogus));
+    assert!(matches!(res, Resolution::Bogus(_)), "{res:?}");
+    assert_eq!(res.rcode(), ResponseCode::ServFail);
+    assert!(!res.is_retryable());
+
+    let res = Resolution::from_error(nsec(Proof::Indeterminate));
+    assert!(matches!(res, Resolution::ServFail(_)), "{res:?}");
+}
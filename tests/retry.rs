@@ -0,0 +1,59 @@
+// Política de reintentos/deadline: tiempos cortos reales, sin red.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use rust_dns_recursor::config::ResolutionConfig;
+use rust_dns_recursor::retry::RetryPolicy;
+
+fn policy(deadline_ms: u64, attempts: u32) -> RetryPolicy {
+    RetryPolicy::from_config(&ResolutionConfig {
+        deadline_ms,
+        attempts,
+        backoff_base_ms: 5,
+        backoff_max_ms: 20,
+        jitter: 0.0,
+    })
+}
+
+#[test]
+fn backoff_is_exponential_and_capped() {
+    let p = policy(1000, 3);
+    assert_eq!(p.backoff(0), Duration::from_millis(5));
+    assert_eq!(p.backoff(1), Duration::from_millis(10));
+    assert_eq!(p.backoff(5), Duration::from_millis(20));
+    assert_eq!(p.backoff(40), Duration::from_millis(20));
+}
+
+#[tokio::test]
+async fn only_retryable_results_are_retried() {
+    let calls = AtomicU32::new(0);
+    let op = || async {
+        calls.fetch_add(1, Ordering::Relaxed);
+        "nxdomain"
+    };
+    let res = policy(1000, 3).run(op, |r| *r == "servfail", || "deadline").await;
+    assert_eq!(res, "nxdomain");
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+    let calls = AtomicU32::new(0);
+    let op = || async {
+        calls.fetch_add(1, Ordering::Relaxed);
+        "servfail"
+    };
+    let res = policy(1000, 3).run(op, |r| *r == "servfail", || "deadline").await;
+    assert_eq!(res, "servfail");
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+}
+
+#[tokio::test]
+async fn deadline_bounds_the_whole_resolution() {
+    let started = Instant::now();
+    let op = || async {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        "answer"
+    };
+    let res = policy(50, 5).run(op, |_| true, || "deadline").await;
+    assert_eq!(res, "deadline");
+    assert!(started.elapsed() < Duration::from_millis(300));
+}
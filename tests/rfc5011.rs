@@ -0,0 +1,174 @@
+// Rollover RFC 5011 del anchor de la raíz: estados y persistencia, sin red.
+#![cfg(feature = "dnssec")]
+
+use std::time::Duration;
+
+use hickory_proto::dnssec::crypto::EcdsaSigningKey;
+use hickory_proto::dnssec::rdata::{DNSSECRData, DNSKEY, RRSIG};
+use hickory_proto::dnssec::tbs::TBS;
+use hickory_proto::dnssec::{Algorithm, SigningKey};
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+
+use rust_dns_recursor::rfc5011::{AnchorState, KeyState};
+
+const NOW: u64 = 1_800_000_000;
+const HOLD: u64 = 100;
+
+struct Ksk {
+    signer: EcdsaSigningKey,
+    key: DNSKEY,
+}
+
+impl Ksk {
+    fn generate() -> Self {
+        let alg = Algorithm::ECDSAP256SHA256;
+        let signer = EcdsaSigningKey::from_pkcs8(&EcdsaSigningKey::generate_pkcs8(alg).unwrap(), alg).unwrap();
+        let key = DNSKEY::with_flags(257, signer.to_public_key().unwrap());
+        Self { signer, key }
+    }
+
+    fn revoked(&self) -> DNSKEY {
+        DNSKEY::with_flags(257 | 0x80, self.key.public_key().clone())
+    }
+}
+
+fn dnskey(key: &DNSKEY) -> Record {
+    Record::from_rdata(Name::root(), 172800, RData::DNSSEC(DNSSECRData::DNSKEY(key.clone())))
+}
+
+/// Respuesta a `. DNSKEY`: el RRset firmado por cada `(firmante, clave publicada)`.
+fn response(keys: &[&DNSKEY], signers: &[(&Ksk, &DNSKEY)], now: u64) -> Vec<Record> {
+    let rrset: Vec<Record> = keys.iter().map(|k| dnskey(k)).collect();
+    let mut out = rrset.clone();
+    for (ksk, published) in signers {
+        let rrsig = |sig: Vec<u8>| {
+            RRSIG::new(
+                RecordType::DNSKEY,
+                Algorithm::ECDSAP256SHA256,
+                0,
+                172800,
+                (now + 86400) as u32,
+                (now - 3600) as u32,
+                published.calculate_key_tag().unwrap(),
+                Name::root(),
+                sig,
+            )
+        };
+        let tbs = TBS::from_sig(&Name::root(), DNSClass::IN, &rrsig(Vec::new()), rrset.iter()).unwrap();
+        let sig = ksk.signer.sign(&tbs).unwrap();
+        out.push(Record::from_rdata(
+            Name::root(),
+            172800,
+            RData::DNSSEC(DNSSECRData::RRSIG(rrsig(sig))),
+        ));
+    }
+    out
+}
+
+fn state_of(st: &AnchorState, key: &DNSKEY) -> Option<KeyState> {
+    st.keys
+        .iter()
+        .find(|t| t.key.public_key() == key.public_key())
+        .map(|t| t.state)
+}
+
+fn seeded(ksk: &Ksk) -> AnchorState {
+    AnchorState::seed([ksk.key.clone()], NOW).with_hold_down(Duration::from_secs(HOLD), Duration::from_secs(HOLD))
+}
+
+#[test]
+fn new_key_is_trusted_only_after_add_hold_down() -> anyhow::Result<()> {
+    let (a, b) = (Ksk::generate(), Ksk::generate());
+    let mut st = seeded(&a);
+
+    let changes = st.observe(&response(&[&a.key, &b.key], &[(&a, &a.key)], NOW), NOW)?;
+    assert!(changes.state && !changes.trusted);
+    assert_eq!(state_of(&st, &b.key), Some(KeyState::AddPend));
+    assert_eq!(st.trusted().len(), 1);
+
+    let later = NOW + HOLD / 2;
+    assert!(!st.observe(&response(&[&a.key, &b.key], &[(&a, &a.key)], later), later)?.trusted);
+    assert_eq!(state_of(&st, &b.key), Some(KeyState::AddPend));
+
+    let later = NOW + HOLD;
+    assert!(st.observe(&response(&[&a.key, &b.key], &[(&a, &a.key)], later), later)?.trusted);
+    assert_eq!(state_of(&st, &b.key), Some(KeyState::Valid));
+    assert_eq!(st.trusted().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn pending_key_that_disappears_starts_over() -> anyhow::Result<()> {
+    let (a, b) = (Ksk::generate(), Ksk::generate());
+    let mut st = seeded(&a);
+
+    st.observe(&response(&[&a.key, &b.key], &[(&a, &a.key)], NOW), NOW)?;
+    let later = NOW + HOLD / 2;
+    st.observe(&response(&[&a.key], &[(&a, &a.key)], later), later)?;
+    assert_eq!(state_of(&st, &b.key), None);
+
+    let later = NOW + HOLD;
+    st.observe(&response(&[&a.key, &b.key], &[(&a, &a.key)], later), later)?;
+    assert_eq!(state_of(&st, &b.key), Some(KeyState::AddPend));
+    Ok(())
+}
+
+#[test]
+fn rrset_not_signed_by_a_trust_anchor_is_ignored() {
+    let (a, b) = (Ksk::generate(), Ksk::generate());
+    let mut st = seeded(&a);
+
+    for records in [
+        response(&[&a.key, &b.key], &[], NOW),
+        response(&[&a.key, &b.key], &[(&b, &b.key)], NOW),
+        // Firma vencida.
+        response(&[&a.key, &b.key], &[(&a, &a.key)], NOW - 2 * 86400),
+    ] {
+        assert!(st.observe(&records, NOW).is_err());
+        assert_eq!(state_of(&st, &b.key), None);
+    }
+}
+
+#[test]
+fn self_signed_revocation_removes_trust() -> anyhow::Result<()> {
+    let (a, b) = (Ksk::generate(), Ksk::generate());
+    let mut st = AnchorState::seed([a.key.clone(), b.key.clone()], NOW)
+        .with_hold_down(Duration::from_secs(HOLD), Duration::from_secs(HOLD));
+
+    // REVOKE sin autofirma: no cuenta.
+    let revoked = a.revoked();
+    st.observe(&response(&[&revoked, &b.key], &[(&b, &b.key)], NOW), NOW)?;
+    assert_eq!(state_of(&st, &a.key), Some(KeyState::Valid));
+
+    let changes = st.observe(&response(&[&revoked, &b.key], &[(&b, &b.key), (&a, &revoked)], NOW), NOW)?;
+    assert!(changes.trusted);
+    assert_eq!(state_of(&st, &a.key), Some(KeyState::Revoked));
+    assert_eq!(st.trusted(), [&b.key]);
+
+    // Pasado el remove hold-down se olvida.
+    let later = NOW + HOLD;
+    st.observe(&response(&[&b.key], &[(&b, &b.key)], later), later)?;
+    assert_eq!(state_of(&st, &a.key), None);
+    Ok(())
+}
+
+#[test]
+fn state_round_trips_through_file() -> anyhow::Result<()> {
+    let (a, b) = (Ksk::generate(), Ksk::generate());
+    let mut st = seeded(&a);
+    st.observe(&response(&[&a.key, &b.key], &[(&a, &a.key)], NOW), NOW)?;
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("root-anchors.state");
+    st.save(&path)?;
+    let loaded = AnchorState::load(&path)?;
+    assert_eq!(loaded.keys.len(), 2);
+    assert_eq!(state_of(&loaded, &a.key), Some(KeyState::Valid));
+    assert_eq!(state_of(&loaded, &b.key), Some(KeyState::AddPend));
+    assert_eq!(loaded.keys[1].first_seen, NOW);
+
+    // Sin ninguna clave confiable el archivo no sirve.
+    let txt = st.to_text().replace("state=VALID", "state=REVOKED");
+    assert!(AnchorState::parse(&txt).is_err());
+    Ok(())
+}
@@ -0,0 +1,226 @@
+// Copia local de la raíz (RFC 8806): parseo, ZONEMD y respuestas, sin red.
+#![cfg(feature = "dnssec")]
+
+use hickory_proto::dnssec::crypto::EcdsaSigningKey;
+use hickory_proto::dnssec::rdata::{DNSSECRData, DNSKEY, RRSIG};
+use hickory_proto::dnssec::tbs::TBS;
+use hickory_proto::dnssec::{Algorithm, SigningKey};
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::rdata::NULL;
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+
+use rust_dns_recursor::root_mirror::{RootZone, ZONEMD};
+
+const ZONE: &str = "\
+; copia de prueba
+.\t86400\tIN\tSOA\ta.root-servers.net. nstld.verisign-grs.com. 2026101800 1800 900 604800 86400
+.\t518400\tIN\tNS\ta.root-servers.net.
+.\t86400\tIN\tNSEC\taaa. NS SOA RRSIG NSEC DNSKEY ZONEMD
+.\t86400\tIN\tZONEMD\t2026101800 1 1 00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff 00112233445566778899aabbccddeeff
+com.\t172800\tIN\tNS\ta.gtld-servers.net.
+com.\t86400\tIN\tNSEC\tnet. NS DS RRSIG NSEC
+net.\t172800\tIN\tNS\ta.gtld-servers.net.
+net.\t86400\tIN\tNSEC\t. NS RRSIG NSEC
+a.gtld-servers.net.\t172800\tIN\tA\t192.5.6.30
+a.root-servers.net.\t518400\tIN\tA\t198.41.0.4
+";
+
+fn name(s: &str) -> Name {
+    Name::from_ascii(s).unwrap()
+}
+
+#[test]
+fn parses_zone_with_zonemd() -> anyhow::Result<()> {
+    let zone = RootZone::parse(ZONE)?;
+    assert_eq!(zone.serial, 2026101800);
+    assert_eq!(zone.expire.as_secs(), 604800);
+    assert_eq!(zone.record_count(), 10);
+
+    let nsec = zone.lookup(&Name::root(), RecordType::NSEC, false).answers;
+    let RData::DNSSEC(DNSSECRData::NSEC(nsec)) = nsec[0].data() else {
+        panic!("no es NSEC: {nsec:?}");
+    };
+    assert!(nsec.type_bit_maps().any(|t| t == ZONEMD));
+    assert!(!nsec.type_bit_maps().any(|t| t == RecordType::CSYNC));
+    Ok(())
+}
+
+#[test]
+fn rejects_missing_or_wrong_zonemd() -> anyhow::Result<()> {
+    let err = RootZone::parse(ZONE)?.verify_zonemd().unwrap_err();
+    assert!(err.to_string().contains("no coincide"), "{err}");
+
+    let stale = ZONE.replace("ZONEMD\t2026101800", "ZONEMD\t2026101700");
+    let err = RootZone::parse(&stale)?.verify_zonemd().unwrap_err();
+    assert!(err.to_string().contains("serial"), "{err}");
+
+    let without: String = ZONE.lines().filter(|l| !l.contains("\tZONEMD\t")).map(|l| format!("{l}\n")).collect();
+    assert!(RootZone::parse(&without)?.verify_zonemd().is_err());
+    Ok(())
+}
+
+#[test]
+fn parses_dnssec_records_in_presentation_format() -> anyhow::Result<()> {
+    let ksk = Key::generate();
+    let extra = format!(
+        ".\t172800\tIN\tDNSKEY\t{}\n\
+         .\t86400\tIN\tRRSIG\tZONEMD 13 0 86400 20261101000000 20261018000000 {} . AAECAw==\n",
+        ksk.dnskey,
+        ksk.dnskey.calculate_key_tag()?
+    );
+    let zone = RootZone::parse(&format!("{ZONE}{extra}"))?;
+
+    let keys = zone.lookup(&Name::root(), RecordType::DNSKEY, true).answers;
+    assert!(keys.iter().any(|r| r.data() == &RData::DNSSEC(DNSSECRData::DNSKEY(ksk.dnskey.clone()))));
+
+    let sigs: Vec<RRSIG> = zone
+        .lookup(&Name::root(), ZONEMD, true)
+        .answers
+        .iter()
+        .filter_map(|r| match r.data() {
+            RData::DNSSEC(DNSSECRData::RRSIG(sig)) => Some(sig.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sigs.len(), 1);
+    assert_eq!(sigs[0].type_covered(), ZONEMD);
+    assert_eq!(sigs[0].sig_inception().get(), 1_792_281_600);
+    assert_eq!(sigs[0].sig_expiration().get() - sigs[0].sig_inception().get(), 14 * 86400);
+    assert_eq!(sigs[0].sig(), &[0, 1, 2, 3]);
+    Ok(())
+}
+
+#[test]
+fn rejects_malformed_zones() {
+    assert!(RootZone::parse("com. IN NS a.gtld-servers.net.").is_err());
+    assert!(RootZone::parse("com.\t172800\tIN\tNS\ta.gtld-servers.net.").is_err());
+}
+
+#[test]
+fn refers_tld_queries_with_glue() -> anyhow::Result<()> {
+    let zone = RootZone::parse(ZONE)?;
+    let reply = zone.lookup(&name("www.Example.COM."), RecordType::A, false);
+    assert_eq!(reply.rcode, ResponseCode::NoError);
+    assert!(!reply.authoritative);
+    assert!(reply.answers.is_empty());
+    assert_eq!(reply.authority.len(), 1);
+    assert_eq!(reply.authority[0].record_type(), RecordType::NS);
+    assert_eq!(reply.additionals.len(), 1);
+    assert_eq!(reply.additionals[0].name(), &name("a.gtld-servers.net."));
+
+    // Con DO va la prueba de que net. no tiene DS.
+    let reply = zone.lookup(&name("example.net."), RecordType::A, true);
+    assert!(reply.authority.iter().any(|r| r.record_type() == RecordType::NSEC));
+    Ok(())
+}
+
+#[test]
+fn answers_apex_and_nonexistent_tlds() -> anyhow::Result<()> {
+    let zone = RootZone::parse(ZONE)?;
+
+    let reply = zone.lookup(&Name::root(), RecordType::SOA, false);
+    assert!(reply.authoritative);
+    assert_eq!(reply.answers.len(), 1);
+
+    let reply = zone.lookup(&Name::root(), RecordType::AAAA, true);
+    assert_eq!(reply.rcode, ResponseCode::NoError);
+    assert!(reply.answers.is_empty());
+    assert!(reply.authority.iter().any(|r| r.record_type() == RecordType::SOA));
+    assert!(reply.authority.iter().any(|r| r.record_type() == RecordType::NSEC));
+
+    let reply = zone.lookup(&name("www.invalid."), RecordType::A, true);
+    assert_eq!(reply.rcode, ResponseCode::NXDomain);
+    // SOA + NSEC que cubre invalid. + NSEC del apex (comodín).
+    assert_eq!(reply.authority.len(), 3, "{:?}", reply.authority);
+    Ok(())
+}
+
+const NOW: u64 = 1_800_000_000;
+
+struct Key {
+    signer: EcdsaSigningKey,
+    dnskey: DNSKEY,
+}
+
+impl Key {
+    fn generate() -> Self {
+        let alg = Algorithm::ECDSAP256SHA256;
+        let signer = EcdsaSigningKey::from_pkcs8(&EcdsaSigningKey::generate_pkcs8(alg).unwrap(), alg).unwrap();
+        let dnskey = DNSKEY::with_flags(257, signer.to_public_key().unwrap());
+        Self { signer, dnskey }
+    }
+
+    /// RRSIG del RRset (todos de `.` y del mismo tipo), vigente en `NOW`.
+    fn sign(&self, rrset: &[Record]) -> Record {
+        let rrsig = |sig: Vec<u8>| {
+            RRSIG::new(
+                rrset[0].record_type(),
+                Algorithm::ECDSAP256SHA256,
+                0,
+                86400,
+                (NOW + 86400) as u32,
+                (NOW - 3600) as u32,
+                self.dnskey.calculate_key_tag().unwrap(),
+                Name::root(),
+                sig,
+            )
+        };
+        let tbs = TBS::from_sig(&Name::root(), DNSClass::IN, &rrsig(Vec::new()), rrset.iter()).unwrap();
+        let sig = self.signer.sign(&tbs).unwrap();
+        Record::from_rdata(Name::root(), 86400, RData::DNSSEC(DNSSECRData::RRSIG(rrsig(sig))))
+    }
+}
+
+/// `ZONE` con DNSKEY (de `ksk`) y ZONEMD firmados por `zonemd_signer`.
+fn signed_zone(ksk: &Key, zonemd_signer: &Key) -> anyhow::Result<RootZone> {
+    let base = RootZone::parse(ZONE)?;
+    let mut records: Vec<Record> = [".", "com.", "net.", "a.gtld-servers.net.", "a.root-servers.net."]
+        .iter()
+        .flat_map(|n| {
+            [RecordType::SOA, RecordType::NS, RecordType::NSEC, RecordType::A]
+                .into_iter()
+                .flat_map(|t| base.lookup(&name(n), t, false).answers)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let dnskey = vec![Record::from_rdata(
+        Name::root(),
+        86400,
+        RData::DNSSEC(DNSSECRData::DNSKEY(ksk.dnskey.clone())),
+    )];
+    let zonemd = vec![Record::from_rdata(
+        Name::root(),
+        86400,
+        RData::Unknown {
+            code: ZONEMD,
+            rdata: NULL::with(vec![0x78, 0xc4, 0x2a, 0x38, 1, 1, 0xaa]),
+        },
+    )];
+    records.push(ksk.sign(&dnskey));
+    records.push(zonemd_signer.sign(&zonemd));
+    records.extend(dnskey);
+    records.extend(zonemd);
+    RootZone::from_records(records)
+}
+
+#[test]
+fn zonemd_signature_chains_to_a_trust_anchor() -> anyhow::Result<()> {
+    let (ksk, other) = (Key::generate(), Key::generate());
+
+    let zone = signed_zone(&ksk, &ksk)?;
+    zone.verify_zonemd_signature(std::slice::from_ref(&ksk.dnskey), NOW)?;
+
+    // DNSKEY de la copia firmado por una clave que no es anchor.
+    let err = zone.verify_zonemd_signature(std::slice::from_ref(&other.dnskey), NOW).unwrap_err();
+    assert!(err.to_string().contains("trust anchor"), "{err}");
+
+    // Firmas vencidas.
+    let err = zone.verify_zonemd_signature(std::slice::from_ref(&ksk.dnskey), NOW + 2 * 86400).unwrap_err();
+    assert!(err.to_string().contains("trust anchor"), "{err}");
+
+    // ZONEMD firmado por una clave que no está en el DNSKEY de la zona.
+    let zone = signed_zone(&ksk, &other)?;
+    let err = zone.verify_zonemd_signature(std::slice::from_ref(&ksk.dnskey), NOW).unwrap_err();
+    assert!(err.to_string().contains("ZONEMD"), "{err}");
+    Ok(())
+}
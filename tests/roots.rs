@@ -0,0 +1,120 @@
+// Root hints: IPs sueltas, ip:port y archivos named.root; respuesta de priming.
+
+use std::net::SocketAddr;
+
+use hickory_proto::op::{Message, ResponseCode};
+use hickory_proto::rr::rdata::{A, AAAA, NS};
+use hickory_proto::rr::{Name, RData, Record};
+
+use rust_dns_recursor::roots;
+
+const NAMED_ROOT: &str = "\
+;       This file holds the information on root name servers needed to
+;       initialize cache of Internet domain name servers
+.                        3600000      NS    A.ROOT-SERVERS.NET.
+A.ROOT-SERVERS.NET.      3600000      A     198.41.0.4
+A.ROOT-SERVERS.NET.      3600000      AAAA  2001:503:ba3e::2:30
+;
+.                        3600000      NS    B.ROOT-SERVERS.NET.
+B.ROOT-SERVERS.NET.      3600000  IN  A     170.247.170.2 ; con clase
+; End of file
+";
+
+fn sa(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn parses_named_root_addresses() {
+    let ips = roots::parse_hints(NAMED_ROOT);
+    let got: Vec<String> = ips.iter().map(|ip| ip.to_string()).collect();
+    assert_eq!(got, ["198.41.0.4", "2001:503:ba3e::2:30", "170.247.170.2"]);
+}
+
+#[test]
+fn mixes_ips_lab_ports_and_files() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("named.root");
+    std::fs::write(&path, NAMED_ROOT)?;
+
+    let entries = vec![
+        "192.0.2.53".to_string(),
+        "127.0.0.1:5300".to_string(),
+        "[::1]:5301".to_string(),
+        path.display().to_string(),
+        "198.41.0.4".to_string(),
+    ];
+    let got = roots::load(&entries)?;
+    assert_eq!(
+        got,
+        [
+            sa("192.0.2.53:53"),
+            sa("127.0.0.1:5300"),
+            sa("[::1]:5301"),
+            sa("198.41.0.4:53"),
+            sa("[2001:503:ba3e::2:30]:53"),
+            sa("170.247.170.2:53"),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn rejects_missing_or_empty_files() -> anyhow::Result<()> {
+    assert!(roots::load(&["/no/existe/named.root".to_string()]).is_err());
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("vacio.hints");
+    std::fs::write(&path, "; nada\n.  3600000  NS  A.ROOT-SERVERS.NET.\n")?;
+    assert!(roots::load(&[path.display().to_string()]).is_err());
+    Ok(())
+}
+
+fn priming_response() -> Message {
+    let root_ns = |ns: &str| Record::from_rdata(Name::root(), 518400, RData::NS(NS(Name::from_ascii(ns).unwrap())));
+    let glue = |ns: &str, data: RData| Record::from_rdata(Name::from_ascii(ns).unwrap(), 518400, data);
+    let mut msg = Message::new();
+    msg.add_answer(root_ns("a.root-servers.net."));
+    msg.add_answer(root_ns("b.root-servers.net."));
+    msg.add_additional(glue("a.root-servers.net.", RData::A(A("198.41.0.4".parse().unwrap()))));
+    msg.add_additional(glue("a.root-servers.net.", RData::AAAA(AAAA("2001:503:ba3e::2:30".parse().unwrap()))));
+    msg.add_additional(glue("b.root-servers.net.", RData::A(A("170.247.170.2".parse().unwrap()))));
+    // Glue de un nombre que no es NS de la raíz: se ignora.
+    msg.add_additional(glue("x.example.", RData::A(A("192.0.2.1".parse().unwrap()))));
+    msg
+}
+
+#[test]
+fn primes_from_ns_and_glue() -> anyhow::Result<()> {
+    let primed = roots::primed_from(&priming_response())?;
+    assert_eq!(
+        primed.addrs,
+        [sa("198.41.0.4:53"), sa("[2001:503:ba3e::2:30]:53"), sa("170.247.170.2:53")]
+    );
+    assert_eq!(primed.ttl, 518400);
+    Ok(())
+}
+
+#[test]
+fn rejects_unusable_priming_responses() {
+    let mut refused = priming_response();
+    refused.set_response_code(ResponseCode::Refused);
+    assert!(roots::primed_from(&refused).is_err());
+
+    let mut no_glue = priming_response();
+    no_glue.take_additionals();
+    assert!(roots::primed_from(&no_glue).is_err());
+
+    assert!(roots::primed_from(&Message::new()).is_err());
+}
+
+#[test]
+fn reports_hint_divergence() {
+    let primed = [sa("198.41.0.4:53"), sa("170.247.170.2:53")];
+    assert_eq!(roots::divergence(&primed, &primed), None);
+
+    let hints = [sa("198.41.0.4:53"), sa("199.9.14.201:53")];
+    let diff = roots::divergence(&hints, &primed).unwrap();
+    assert!(diff.contains("faltan en los hints [170.247.170.2]"), "{diff}");
+    assert!(diff.contains("sobran [199.9.14.201]"), "{diff}");
+}
@@ -0,0 +1,163 @@
+// Cache por RRset: cadenas CNAME armadas desde el cache, TTL por RRset,
+// confianza y RRSIG sólo para clientes con DO.
+
+use hickory_proto::rr::rdata::{A, CNAME};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use rust_dns_recursor::cache::CacheKey;
+use rust_dns_recursor::config::{CacheConfig, NegativeCacheConfig};
+use rust_dns_recursor::rrset_cache::{RrsetCache, Trust};
+
+fn cache() -> RrsetCache {
+    cache_with_chain(8)
+}
+
+fn cache_with_chain(max_chain: usize) -> RrsetCache {
+    RrsetCache::new(&CacheConfig {
+        answer_cache_size: 100,
+        negative_cache_size: 100,
+        min_ttl: 10,
+        max_ttl: 3600,
+        negative_ttl: 60,
+        prefetch_threshold_secs: 5,
+        stale_window_secs: 60,
+        negative: NegativeCacheConfig::default(),
+    }, max_chain)
+}
+
+fn key(name: &str, qtype: RecordType, do_bit: bool) -> CacheKey {
+    CacheKey {
+        qname_lc: name.to_string(),
+        qtype: qtype.into(),
+        do_bit,
+        subnet: None,
+    }
+}
+
+fn name(s: &str) -> Name {
+    Name::from_ascii(s).unwrap()
+}
+
+fn a(owner: &str, ttl: u32, last: u8) -> Record {
+    Record::from_rdata(name(owner), ttl, RData::A(A::new(192, 0, 2, last)))
+}
+
+fn cname(owner: &str, ttl: u32, target: &str) -> Record {
+    Record::from_rdata(name(owner), ttl, RData::CNAME(CNAME(name(target))))
+}
+
+/// RRSIG que cubre A: sin el feature dnssec viaja como RData desconocida.
+#[cfg(not(feature = "dnssec"))]
+fn rrsig_a(owner: &str, ttl: u32) -> Record {
+    use hickory_proto::rr::rdata::NULL;
+
+    let mut rdata = u16::from(RecordType::A).to_be_bytes().to_vec();
+    rdata.extend_from_slice(&[0; 16]);
+    Record::from_rdata(
+        name(owner),
+        ttl,
+        RData::Unknown {
+            code: RecordType::RRSIG,
+            rdata: NULL::with(rdata),
+        },
+    )
+}
+
+#[tokio::test]
+async fn cname_target_serves_direct_queries() {
+    let c = cache();
+    let answers = [
+        cname("www.example.com.", 3600, "cdn.example.net."),
+        a("cdn.example.net.", 60, 1),
+    ];
+    assert!(c.insert_answer(&key("www.example.com", RecordType::A, false), &answers, Trust::Insecure).await);
+
+    let direct = c.lookup(&key("cdn.example.net", RecordType::A, false)).await.unwrap();
+    assert_eq!(direct.answers.len(), 1);
+    assert_eq!(direct.answers[0].record_type(), RecordType::A);
+
+    // La cadena se arma desde el cache; cada RRset con su TTL.
+    let chained = c.lookup(&key("www.example.com", RecordType::A, false)).await.unwrap();
+    assert_eq!(chained.answers.len(), 2);
+    assert!(chained.answers[0].ttl() > 60, "{:?}", chained.answers);
+    assert!(chained.answers[1].ttl() <= 60, "{:?}", chained.answers);
+    assert!(chained.expires_at <= direct.expires_at);
+
+    // El CNAME solo también está.
+    let only = c.lookup(&key("www.example.com", RecordType::CNAME, false)).await.unwrap();
+    assert_eq!(only.answers.len(), 1);
+}
+
+#[tokio::test]
+async fn ignores_records_off_the_chain() {
+    let c = cache();
+    let answers = [a("www.example.com.", 300, 1), a("bank.example.org.", 300, 66)];
+    assert!(c.insert_answer(&key("www.example.com", RecordType::A, false), &answers, Trust::Insecure).await);
+    assert!(c.lookup(&key("bank.example.org", RecordType::A, false)).await.is_none());
+}
+
+#[tokio::test]
+async fn dangling_cname_is_not_a_positive_answer() {
+    let c = cache();
+    let answers = [cname("www.example.com.", 300, "gone.example.net.")];
+    assert!(!c.insert_answer(&key("www.example.com", RecordType::A, false), &answers, Trust::Insecure).await);
+    assert!(c.lookup(&key("www.example.com", RecordType::A, false)).await.is_none());
+}
+
+#[tokio::test]
+async fn chains_longer_than_the_limit_are_not_assembled() {
+    let c = cache_with_chain(1);
+    let one = [cname("a.example.com.", 300, "b.example.com."), a("b.example.com.", 300, 1)];
+    assert!(c.insert_answer(&key("a.example.com", RecordType::A, false), &one, Trust::Insecure).await);
+    assert!(c.lookup(&key("a.example.com", RecordType::A, false)).await.is_some());
+
+    let two = [
+        cname("x.example.com.", 300, "y.example.com."),
+        cname("y.example.com.", 300, "z.example.com."),
+        a("z.example.com.", 300, 1),
+    ];
+    assert!(!c.insert_answer(&key("x.example.com", RecordType::A, false), &two, Trust::Insecure).await);
+    assert!(c.lookup(&key("x.example.com", RecordType::A, false)).await.is_none());
+}
+
+#[tokio::test]
+async fn ttls_are_clamped_per_rrset() {
+    let c = cache();
+    assert!(c.insert_answer(&key("short.example.com", RecordType::A, false), &[a("short.example.com.", 1, 1)], Trust::Insecure).await);
+    let hit = c.lookup(&key("short.example.com", RecordType::A, false)).await.unwrap();
+    assert!(hit.answers[0].ttl() > 1 && hit.answers[0].ttl() <= 10, "{:?}", hit.answers);
+}
+
+#[tokio::test]
+async fn trust_is_the_weakest_of_the_chain_and_not_downgraded() {
+    let c = cache();
+    let k = key("www.example.com", RecordType::A, false);
+    c.insert_answer(&key("cdn.example.net", RecordType::A, false), &[a("cdn.example.net.", 300, 1)], Trust::Secure)
+        .await;
+    c.insert_answer(&k, &[cname("www.example.com.", 300, "cdn.example.net.")], Trust::Insecure)
+        .await;
+    assert_eq!(c.lookup(&k).await.unwrap().trust, Trust::Insecure);
+
+    // Un RRset validado vigente no se pisa con uno sin validar.
+    c.insert_answer(&key("cdn.example.net", RecordType::A, false), &[a("cdn.example.net.", 300, 2)], Trust::Insecure)
+        .await;
+    let hit = c.lookup(&key("cdn.example.net", RecordType::A, false)).await.unwrap();
+    assert_eq!(hit.trust, Trust::Secure);
+    assert_eq!(hit.answers[0].data(), &RData::A(A::new(192, 0, 2, 1)));
+}
+
+#[cfg(not(feature = "dnssec"))]
+#[tokio::test]
+async fn rrsigs_follow_their_rrset_and_need_do() {
+    let c = cache();
+    let answers = [a("www.example.com.", 300, 1), rrsig_a("www.example.com.", 300)];
+
+    // Guardado sin DO: no sirve a un cliente con DO.
+    c.insert_answer(&key("www.example.com", RecordType::A, false), &answers[..1], Trust::Insecure).await;
+    assert!(c.lookup(&key("www.example.com", RecordType::A, true)).await.is_none());
+
+    c.insert_answer(&key("www.example.com", RecordType::A, true), &answers, Trust::Insecure).await;
+    let signed = c.lookup(&key("www.example.com", RecordType::A, true)).await.unwrap();
+    assert_eq!(signed.answers.len(), 2);
+    let plain = c.lookup(&key("www.example.com", RecordType::A, false)).await.unwrap();
+    assert_eq!(plain.answers.len(), 1);
+}
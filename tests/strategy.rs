@@ -0,0 +1,108 @@
+// Estrategias de selección de upstreams y snapshot de estadísticas: tests
+// deterministas (RNG con semilla, sin red).
+
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rust_dns_recursor::forwarder::{weighted_shuffle, Strategy};
+use rust_dns_recursor::upstream::{Outcome, UpstreamStats, LATENCY_BUCKETS_MS};
+
+fn rng() -> StdRng {
+    StdRng::seed_from_u64(7)
+}
+
+#[test]
+fn parses_strategy_names_and_aliases() {
+    assert_eq!(Strategy::parse("ordered").unwrap(), Strategy::Ordered);
+    assert_eq!(Strategy::parse(" Round_Robin ").unwrap(), Strategy::RoundRobin);
+    assert_eq!(Strategy::parse("weighted").unwrap(), Strategy::Random);
+    assert_eq!(Strategy::parse("srtt").unwrap(), Strategy::LowestSrtt);
+    assert!(Strategy::parse("fastest").is_err());
+}
+
+#[test]
+fn ordered_keeps_configuration_order() {
+    let order = Strategy::Ordered.order(&[1, 1, 1], &[], 5, &mut rng());
+    assert_eq!(order, vec![0, 1, 2]);
+}
+
+#[test]
+fn round_robin_rotates_the_first_upstream() {
+    let w = [1, 1, 1];
+    let orders: Vec<Vec<usize>> = (0..4)
+        .map(|turn| Strategy::RoundRobin.order(&w, &[], turn, &mut rng()))
+        .collect();
+    assert_eq!(orders, vec![vec![0, 1, 2], vec![1, 2, 0], vec![2, 0, 1], vec![0, 1, 2]]);
+}
+
+#[test]
+fn lowest_srtt_tries_unmeasured_first_then_fastest() {
+    let srtts = [
+        Some(Duration::from_millis(40)),
+        Some(Duration::from_millis(5)),
+        None,
+        Some(Duration::from_millis(20)),
+    ];
+    let order = Strategy::LowestSrtt.order(&[1, 1, 1, 1], &srtts, 0, &mut rng());
+    assert_eq!(order, vec![2, 1, 3, 0]);
+}
+
+#[test]
+fn random_is_a_permutation_and_reproducible_with_a_seed() {
+    let w = [1, 2, 3, 4];
+    let a = Strategy::Random.order(&w, &[], 0, &mut rng());
+    let b = Strategy::Random.order(&w, &[], 0, &mut rng());
+    assert_eq!(a, b);
+
+    let mut sorted = a.clone();
+    sorted.sort();
+    assert_eq!(sorted, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn weighted_shuffle_picks_first_in_proportion_to_weight() {
+    let mut rng = rng();
+    let mut first = [0u32; 2];
+    for _ in 0..10_000 {
+        first[weighted_shuffle(&[1, 3], &mut rng)[0]] += 1;
+    }
+    // Esperado 2500 / 7500.
+    assert!((2200..2800).contains(&first[0]), "{first:?}");
+    assert!((7200..7800).contains(&first[1]), "{first:?}");
+}
+
+#[test]
+fn weighted_shuffle_never_puts_zero_weight_first_while_others_remain() {
+    let mut rng = rng();
+    for _ in 0..1000 {
+        let order = weighted_shuffle(&[0, 1, 0], &mut rng);
+        assert_eq!(order[0], 1);
+        assert_eq!(order.len(), 3);
+    }
+}
+
+#[test]
+fn snapshot_counts_outcomes_latency_buckets_and_srtt() {
+    let s = UpstreamStats::default();
+    assert_eq!(s.snapshot().srtt, None);
+
+    s.record(Outcome::Ok, Duration::from_millis(8));
+    s.record(Outcome::Error, Duration::from_millis(8));
+    s.record(Outcome::Timeout, Duration::from_secs(3));
+    s.record_rejected();
+
+    let snap = s.snapshot();
+    assert_eq!((snap.queries, snap.errors, snap.timeouts, snap.rejected), (3, 1, 1, 1));
+    // 8ms cae en el bucket "<=10ms"; 3s en el abierto "> 2000ms".
+    let b10 = LATENCY_BUCKETS_MS.iter().position(|&ms| ms == 10).unwrap();
+    assert_eq!(snap.latency[b10], 2);
+    assert_eq!(snap.latency[LATENCY_BUCKETS_MS.len()], 1);
+
+    // EWMA 1/8: 8ms, 8ms, luego 3s -> 8 - 1 + 375 = 382ms.
+    assert_eq!(snap.srtt, Some(Duration::from_millis(382)));
+
+    let line = snap.to_string();
+    assert!(line.starts_with("queries=3 errors=1 timeouts=1 rejected=1 srtt=382.0ms"), "{line}");
+    assert!(line.contains("<=10ms:2") && line.contains(">2000ms:1"), "{line}");
+}
@@ -0,0 +1,82 @@
+// Trust anchors desde archivo: parseo y DS -> DNSKEY, sin red.
+#![cfg(feature = "dnssec")]
+
+use hickory_proto::dnssec::rdata::{DNSSECRData, DNSKEY};
+use hickory_proto::dnssec::TrustAnchors;
+use hickory_proto::rr::{Name, RData, Record};
+
+use rust_dns_recursor::trust_anchor::{keys_for_ds, AnchorFile};
+
+/// KSK-2017 de la raíz (tag 20326) y su DS SHA-256 publicado por IANA.
+fn root_ksk() -> DNSKEY {
+    let key = TrustAnchors::default().get(0).unwrap().clone();
+    DNSKEY::with_flags(257, key)
+}
+const ROOT_DS: &str = ". IN DS 20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D";
+
+fn key_line(owner: &str, flags: u16, key: &DNSKEY) -> String {
+    // Mismo formato que escribe `recursor-bootstrap make-trust-anchor`.
+    let rdata = format!("{}", DNSKEY::with_flags(flags, key.public_key().clone()));
+    format!("{owner} 172800 IN DNSKEY {rdata}")
+}
+
+#[test]
+fn parses_dnskey_and_ds_entries() -> anyhow::Result<()> {
+    let ksk = root_ksk();
+    let txt = format!(
+        "; anchors\n{}\n{ROOT_DS}\ncorp.example. IN DS ( 12345 13 2\n  3490A6806D47F17A34C29E2CE80E8A999FFBE4BE )\n",
+        key_line(".", 257, &ksk)
+    );
+    let file = AnchorFile::parse(&txt)?;
+    assert_eq!(file.keys.len(), 1);
+    assert_eq!(file.ds.len(), 2);
+    assert_eq!(file.ds_owners(), [Name::root(), Name::from_ascii("corp.example.")?]);
+    Ok(())
+}
+
+#[test]
+fn malformed_files_are_rejected() {
+    for bad in [
+        "",
+        "; sólo comentarios\n",
+        "corp.example IN DS 12345 13 2 3490A6806D47",
+        ". IN A 192.0.2.1",
+        ". IN DS 20326 8 2 no-es-hex",
+        ". IN DNSKEY 257 3 8 %%%",
+    ] {
+        assert!(AnchorFile::parse(bad).is_err(), "aceptó: {bad:?}");
+    }
+}
+
+#[test]
+fn revoked_keys_are_skipped() -> anyhow::Result<()> {
+    let ksk = root_ksk();
+    let txt = format!("{}\n{}\n", key_line("corp.example.", 257, &ksk), key_line("corp.example.", 385, &ksk));
+    let file = AnchorFile::parse(&txt)?;
+    assert_eq!(file.keys.len(), 1);
+
+    let keys = file.resolve(&[])?;
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].0, Name::from_ascii("corp.example.")?);
+    assert!(!keys[0].1.revoke());
+    Ok(())
+}
+
+#[test]
+fn ds_needs_matching_and_signed_dnskey() -> anyhow::Result<()> {
+    let file = AnchorFile::parse(ROOT_DS)?;
+    let ds: Vec<_> = file.ds.iter().map(|(_, d)| d).collect();
+    let root = Name::root();
+    let ksk = Record::from_rdata(root.clone(), 172800, RData::DNSSEC(DNSSECRData::DNSKEY(root_ksk())));
+
+    // El KSK coincide con el DS pero falta la RRSIG del RRset.
+    let err = keys_for_ds(&root, &ds, &[ksk]).unwrap_err().to_string();
+    assert!(err.contains("no está firmado"), "{err}");
+
+    // Sin DNSKEY que coincida.
+    let other = DNSKEY::with_flags(257, TrustAnchors::default().get(1).unwrap().clone());
+    let other = Record::from_rdata(root.clone(), 172800, RData::DNSSEC(DNSSECRData::DNSKEY(other)));
+    let err = keys_for_ds(&root, &ds, &[other]).unwrap_err().to_string();
+    assert!(err.contains("ningún DNSKEY"), "{err}");
+    Ok(())
+}
@@ -0,0 +1,97 @@
+// Validación de respuestas de upstream: tests deterministas, sin red.
+
+use std::net::Ipv4Addr;
+
+use hickory_proto::op::{Message, Query};
+use hickory_proto::rr::rdata::{A, CNAME, SOA};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+
+use rust_dns_recursor::validation::{Rejection, Validator};
+
+// from_ascii conserva la capitalización (from_str pasa por IDNA y la pierde).
+fn name(s: &str) -> Name {
+    Name::from_ascii(s).unwrap()
+}
+
+fn a(owner: &str, ttl: u32) -> Record {
+    Record::from_rdata(name(owner), ttl, RData::A(A(Ipv4Addr::new(192, 0, 2, 1))))
+}
+
+fn cname(owner: &str, target: &str) -> Record {
+    Record::from_rdata(name(owner), 300, RData::CNAME(CNAME(name(target))))
+}
+
+fn soa(owner: &str) -> Record {
+    let rdata = SOA::new(name("ns.example."), name("hm.example."), 1, 2, 3, 4, 60);
+    Record::from_rdata(name(owner), 300, RData::SOA(rdata))
+}
+
+fn response(q: &Query, answers: Vec<Record>, authority: Vec<Record>) -> Message {
+    let mut m = Message::new();
+    m.add_query(q.clone());
+    m.add_answers(answers);
+    m.add_name_servers(authority);
+    m
+}
+
+#[test]
+fn accepts_cname_chain_and_negative_soa() {
+    let v = Validator::new(86_400);
+    let q = Query::query(name("www.example.com."), RecordType::A);
+
+    let ok = response(
+        &q,
+        vec![cname("www.example.com.", "cdn.example.net."), a("cdn.example.net.", 60)],
+        vec![],
+    );
+    assert_eq!(v.check_message(&q, &ok), Ok(()));
+
+    let nx = response(&q, vec![], vec![soa("example.com.")]);
+    assert_eq!(v.check_message(&q, &nx), Ok(()));
+}
+
+#[test]
+fn rejects_question_case_mismatch() {
+    let v = Validator::new(86_400);
+    let sent = Query::query(name("wWw.ExAmple.com."), RecordType::A);
+    let back = Query::query(name("www.example.com."), RecordType::A);
+
+    let resp = response(&back, vec![a("www.example.com.", 60)], vec![]);
+    assert!(matches!(
+        v.check_message(&sent, &resp),
+        Err(Rejection::QuestionMismatch { .. })
+    ));
+}
+
+#[test]
+fn rejects_out_of_bailiwick_records() {
+    let v = Validator::new(86_400);
+    let q = Query::query(name("www.example.com."), RecordType::A);
+
+    let extra_answer = response(&q, vec![a("www.example.com.", 60), a("bank.example.org.", 60)], vec![]);
+    assert!(matches!(
+        v.check_message(&q, &extra_answer),
+        Err(Rejection::OutOfBailiwick { section: "answer", .. })
+    ));
+
+    let foreign_soa = response(&q, vec![], vec![soa("example.org.")]);
+    assert!(matches!(
+        v.check_message(&q, &foreign_soa),
+        Err(Rejection::OutOfBailiwick { section: "authority", .. })
+    ));
+}
+
+#[test]
+fn rejects_absurd_ttls() {
+    let v = Validator::new(86_400);
+    let records = [a("www.example.com.", 86_401)];
+    assert!(matches!(
+        v.check_records(&name("www.example.com."), &records),
+        Err(Rejection::AbsurdTtl { ttl: 86_401, .. })
+    ));
+
+    // RFC 2181: con el bit alto en 1 siempre se rechaza.
+    let v = Validator::new(u32::MAX);
+    let records = [a("www.example.com.", 0x8000_0000)];
+    assert!(v.check_records(&name("www.example.com."), &records).is_err());
+}